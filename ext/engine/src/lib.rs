@@ -11,6 +11,8 @@ extern crate url;
 #[macro_use]
 extern crate lazy_static;
 
+extern crate rb_sys;
+
 extern crate regex;
 
 extern crate fnv;
@@ -24,6 +26,7 @@ extern crate html5ever;
 pub mod ruby;
 pub mod utilities;
 pub mod uri;
+pub mod public_suffix;
 pub mod support;
 pub mod http;
 pub mod parser;
@@ -1,5 +1,6 @@
-use magnus::{class, method, function, Error, RClass, RModule, Value, RHash, Symbol, TypedData, block::Proc, prelude::*};
+use magnus::{class, method, function, Error, RArray, RClass, RModule, Value, RHash, Symbol, TypedData, block::Proc, prelude::*};
 use parser::sax::*;
+use parser::filter_profile;
 use std::collections::HashMap;
 
 lazy_static! {
@@ -25,6 +26,10 @@ impl Node {
         }
     }
 
+    pub fn from_handle( handle: node::Handle ) -> Self {
+        Node::new( Some(handle) )
+    }
+
     pub fn name( &self ) -> String {
         if let Some( ref handle ) = self.native {
             if let node::Enum::Element { ref name, .. } = handle.borrow().node {
@@ -55,6 +60,38 @@ impl Node {
         hash
     }
 
+    pub fn position( &self ) -> Option<position::Position> {
+        if let Some( ref handle ) = self.native {
+            return handle.position()
+        }
+
+        panic!( "Use after free." );
+    }
+
+    pub fn source_range( &self ) -> Option<position::Range> {
+        if let Some( ref handle ) = self.native {
+            return handle.source_range()
+        }
+
+        panic!( "Use after free." );
+    }
+
+    pub fn forms( &self ) -> Vec<forms::Form> {
+        if let Some( ref handle ) = self.native {
+            return forms::extract( handle )
+        }
+
+        panic!( "Use after free." );
+    }
+
+    pub fn paths( &self ) -> Vec<paths::Path> {
+        if let Some( ref handle ) = self.native {
+            return paths::extract( handle )
+        }
+
+        panic!( "Use after free." );
+    }
+
     pub fn kind( &self ) -> &String {
         if let Some( ref handle ) = self.native {
             return match handle.borrow().node {
@@ -200,6 +237,39 @@ impl Node {
         panic!( "Use after free." );
     }
 
+    pub fn css( &self, selector: &str ) -> Result<Vec<Node>, String> {
+        if let Some( ref handle ) = self.native {
+            let selectors = parse_selector( selector )?;
+
+            let mut nodes = vec![];
+            handle.css( &selectors, |h| nodes.push( Node::new( Some(h.clone()) ) ) );
+            return Ok(nodes)
+        }
+
+        panic!( "Use after free." );
+    }
+
+    pub fn at_css( &self, selector: &str ) -> Result<Option<Node>, String> {
+        if let Some( ref handle ) = self.native {
+            let selectors = parse_selector( selector )?;
+            return Ok(handle.at_css( &selectors ).map( |h| Node::new( Some(h) ) ))
+        }
+
+        panic!( "Use after free." );
+    }
+
+    pub fn xpath( &self, expression: &str ) -> Result<Vec<xpath::Item>, String> {
+        if let Some( ref handle ) = self.native {
+            let xpath = xpath::XPath::parse( expression ).and_then( |x| x.select( handle ) );
+
+            return xpath.map_err( |reason|
+                format!( "Invalid XPath expression '{}': {}", expression, reason )
+            )
+        }
+
+        panic!( "Use after free." );
+    }
+
     pub fn text( &self ) -> String {
         if let Some( ref handle ) = self.native {
             return handle.text()
@@ -221,9 +291,21 @@ impl Node {
     }
 }
 
-fn parse(html: String, filter: bool) -> Node {
+fn parse_selector( selector: &str ) -> Result<selector::SelectorList, String> {
+    selector::SelectorList::parse( selector ).map_err( |reason|
+        format!( "Invalid CSS selector '{}': {}", selector, reason )
+    )
+}
+
+fn parse(html: String, filter: Value, spec_compliant: bool, positions: bool) -> Node {
+    let filter = filter_profile::resolve(filter);
+
     Node::new(
-        Some(parser::parse(&html, filter))
+        Some(if spec_compliant {
+            tree_builder::parse(&html, filter, positions)
+        } else {
+            parser::parse(&html, filter, positions)
+        })
     )
 }
 
@@ -247,6 +329,38 @@ fn node_traverse(rb_self: &Node, cb: Proc) -> Result<bool, Error> {
     Ok(true)
 }
 
+fn node_css(rb_self: &Node, selector: String) -> Result<RArray, Error> {
+    let nodes = rb_self.css(&selector).map_err(|msg| Error::new(magnus::exception::arg_error(), msg))?;
+
+    let array = RArray::with_capacity(nodes.len());
+    for node in nodes {
+        array.push(node)?;
+    }
+    Ok(array)
+}
+
+fn node_at_css(rb_self: &Node, selector: String) -> Result<Option<Node>, Error> {
+    rb_self.at_css(&selector).map_err(|msg| Error::new(magnus::exception::arg_error(), msg))
+}
+
+fn node_xpath(rb_self: &Node, expression: String) -> Result<RArray, Error> {
+    let items = rb_self.xpath(&expression).map_err(|msg| Error::new(magnus::exception::arg_error(), msg))?;
+
+    let array = RArray::with_capacity(items.len());
+    for item in items {
+        match item {
+            // Attributes are returned as their values.
+            xpath::Item::Attribute(..) => array.push(item.string_value())?,
+
+            xpath::Item::Node(handle) => {
+                if let node::Enum::Document = handle.borrow().node { continue }
+                array.push(Node::new(Some(handle)))?
+            }
+        }
+    }
+    Ok(array)
+}
+
 fn node_is_root(rb_self: &Node) -> bool {
     rb_self.is_root()
 }
@@ -263,6 +377,96 @@ fn node_attributes(rb_self: &Node) -> HashMap<String, String> {
     rb_self.attributes()
 }
 
+fn position_to_hash(position: &position::Position) -> Result<RHash, Error> {
+    let hash = RHash::new();
+    hash.aset(Symbol::new("offset"), position.offset)?;
+    hash.aset(Symbol::new("line"), position.line)?;
+    hash.aset(Symbol::new("column"), position.column)?;
+    Ok(hash)
+}
+
+fn node_position(rb_self: &Node) -> Result<Option<RHash>, Error> {
+    match rb_self.position() {
+        Some(position) => Ok(Some(position_to_hash(&position)?)),
+        None           => Ok(None)
+    }
+}
+
+fn node_source_range(rb_self: &Node) -> Result<Option<RHash>, Error> {
+    match rb_self.source_range() {
+        Some(range) => {
+            let hash = RHash::new();
+            hash.aset(Symbol::new("start"), position_to_hash(&range.start)?)?;
+            hash.aset(Symbol::new("end"), position_to_hash(&range.end)?)?;
+            Ok(Some(hash))
+        },
+        None => Ok(None)
+    }
+}
+
+fn attributes_to_hash(attributes: &[(String, String)]) -> Result<RHash, Error> {
+    let hash = RHash::new();
+    for &(ref name, ref value) in attributes {
+        hash.aset(name.to_lowercase(), value.as_str())?;
+    }
+    Ok(hash)
+}
+
+fn field_to_hash(field: &forms::Field) -> Result<RHash, Error> {
+    let options = RArray::with_capacity(field.options.len());
+    for option in &field.options {
+        let hash = RHash::new();
+        hash.aset(Symbol::new("value"), option.value.as_str())?;
+        hash.aset(Symbol::new("text"), option.text.as_str())?;
+        hash.aset(Symbol::new("selected"), option.selected)?;
+        options.push(hash)?;
+    }
+
+    let hash = RHash::new();
+    hash.aset(Symbol::new("tag"), Symbol::new(&*field.tag))?;
+    hash.aset(Symbol::new("name"), field.name.clone())?;
+    hash.aset(Symbol::new("id"), field.id.clone())?;
+    hash.aset(Symbol::new("type"), Symbol::new(&field.kind))?;
+    hash.aset(Symbol::new("value"), field.value.as_str())?;
+    hash.aset(Symbol::new("checked"), field.checked)?;
+    hash.aset(Symbol::new("options"), options)?;
+    hash.aset(Symbol::new("attributes"), attributes_to_hash(&field.attributes)?)?;
+    Ok(hash)
+}
+
+fn node_forms(rb_self: &Node) -> Result<RArray, Error> {
+    let forms = rb_self.forms();
+
+    let array = RArray::with_capacity(forms.len());
+    for form in &forms {
+        let fields = RArray::with_capacity(form.fields.len());
+        for field in &form.fields {
+            fields.push(field_to_hash(field)?)?;
+        }
+
+        let hash = RHash::new();
+        hash.aset(Symbol::new("action"), form.action.clone())?;
+        hash.aset(Symbol::new("method"), Symbol::new(&form.method))?;
+        hash.aset(Symbol::new("enctype"), form.enctype.as_str())?;
+        hash.aset(Symbol::new("id"), form.id.clone())?;
+        hash.aset(Symbol::new("name"), form.name.clone())?;
+        hash.aset(Symbol::new("attributes"), attributes_to_hash(&form.attributes)?)?;
+        hash.aset(Symbol::new("fields"), fields)?;
+        array.push(hash)?;
+    }
+    Ok(array)
+}
+
+fn node_paths(rb_self: &Node) -> Result<RArray, Error> {
+    let paths = rb_self.paths();
+
+    let array = RArray::with_capacity(paths.len());
+    for path in &paths {
+        array.push((path.path.as_str(), Symbol::new(path.extractor.name())))?;
+    }
+    Ok(array)
+}
+
 fn node_kind(rb_self: &Node) -> Symbol {
     Symbol::new(rb_self.kind())
 }
@@ -297,16 +501,23 @@ pub fn initialize() -> Result<(), Error> {
     let parser_ns = rust_ns.define_module("Parser")?;
     let node_class = parser_ns.define_class("Node", class::object())?;
 
-    node_class.define_singleton_method("parse", function!(parse, 2))?;
+    node_class.define_singleton_method("parse", function!(parse, 4))?;
 
     node_class.define_method("nodes_by_name", method!(node_nodes_by_name, 2))?;
     node_class.define_method("nodes_by_attribute_name_and_value", method!(node_nodes_by_attribute_name_and_value, 3))?;
     node_class.define_method("traverse_comments", method!(node_traverse_comments, 1))?;
     node_class.define_method("traverse", method!(node_traverse, 1))?;
+    node_class.define_method("css", method!(node_css, 1))?;
+    node_class.define_method("at_css", method!(node_at_css, 1))?;
+    node_class.define_method("xpath", method!(node_xpath, 1))?;
     node_class.define_method("parent", method!(node_parent, 0))?;
     node_class.define_method("text", method!(node_text, 0))?;
     node_class.define_method("type", method!(node_kind, 0))?;
     node_class.define_method("attributes", method!(node_attributes, 0))?;
+    node_class.define_method("position", method!(node_position, 0))?;
+    node_class.define_method("source_range", method!(node_source_range, 0))?;
+    node_class.define_method("forms", method!(node_forms, 0))?;
+    node_class.define_method("paths", method!(node_paths, 0))?;
     node_class.define_method("name", method!(node_name, 0))?;
     node_class.define_method("root?", method!(node_is_root, 0))?;
     node_class.define_method("free", method!(node_free, 0))?;
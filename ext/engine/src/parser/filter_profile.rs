@@ -0,0 +1,54 @@
+//! Filter profiles for `Node.parse` and `PushParser`, compiled once and shared across parses.
+
+use std::sync::Arc;
+
+use magnus::{class, function, Error, RModule, Value, typed_data, prelude::*};
+use parser::sax::filter;
+
+#[magnus::wrap(class = "SCNR::Engine::Rust::Parser::FilterProfile", free_immediately, size)]
+pub struct FilterProfile {
+    native: Arc<filter::Profile>
+}
+
+impl FilterProfile {
+    fn new( elements: Vec<String>, text_parents: Vec<String>, others: bool ) -> Result<Self, Error> {
+        let profile = filter::Profile::new( &elements, &text_parents, others ).map_err( |msg|
+            Error::new( magnus::exception::arg_error(), msg )
+        )?;
+
+        Ok(FilterProfile {
+            native: Arc::new( profile )
+        })
+    }
+
+    fn named( name: String ) -> Result<Self, Error> {
+        match filter::named( &name ) {
+            Some(profile) => Ok(FilterProfile { native: profile }),
+            None          => Err(Error::new(
+                magnus::exception::arg_error(), format!( "Unknown filter profile '{}'.", name )
+            ))
+        }
+    }
+}
+
+/// Resolves a `filter` argument: a `FilterProfile`, or a boolean toggling the default profile.
+pub fn resolve( filter: Value ) -> Option<Arc<filter::Profile>> {
+    if let Ok(profile) = typed_data::Obj::<FilterProfile>::try_convert( filter ) {
+        return Some(profile.native.clone())
+    }
+
+    if filter.to_bool() { Some(filter::default()) } else { None }
+}
+
+pub fn initialize() -> Result<(), Error> {
+    let scnr_ns = class::object().const_get::<_, RModule>("SCNR")?;
+    let engine_ns = scnr_ns.const_get::<_, RModule>("Engine")?;
+    let rust_ns = engine_ns.define_module("Rust")?;
+    let parser_ns = rust_ns.define_module("Parser")?;
+    let profile_class = parser_ns.define_class("FilterProfile", class::object())?;
+
+    profile_class.define_singleton_method("new", function!(FilterProfile::new, 3))?;
+    profile_class.define_singleton_method("named", function!(FilterProfile::named, 1))?;
+
+    Ok(())
+}
@@ -2,9 +2,13 @@
 
 pub mod sax;
 pub mod document;
+pub mod push_parser;
+pub mod filter_profile;
 
 use magnus::Error;
 
 pub fn initialize() -> Result<(), Error> {
-    document::initialize()
+    filter_profile::initialize()?;
+    document::initialize()?;
+    push_parser::initialize()
 }
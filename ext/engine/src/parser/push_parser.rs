@@ -0,0 +1,65 @@
+//! Incremental parsing, for documents that arrive in chunks.
+
+use std::cell::RefCell;
+
+use magnus::{class, method, function, Error, RModule, RString, Value, prelude::*};
+use parser::sax::parser;
+use parser::document::Node;
+use parser::filter_profile;
+
+#[magnus::wrap(class = "SCNR::Engine::Rust::Parser::PushParser", free_immediately, size)]
+pub struct PushParser {
+    native: RefCell<parser::PushParser>
+}
+
+// SAFETY: Same as `Node`, only used from Ruby threads holding the GVL.
+unsafe impl Send for PushParser {}
+
+impl PushParser {
+    fn new( filter: Value, positions: bool ) -> Self {
+        PushParser {
+            native: RefCell::new( parser::PushParser::new( filter_profile::resolve( filter ), positions ) )
+        }
+    }
+
+    /// Parses the next `chunk`, which may be binary and end in the middle of a character.
+    fn push( &self, chunk: RString ) -> Result<(), Error> {
+        let bytes = unsafe { chunk.as_slice() }.to_vec();
+
+        if !self.native.borrow_mut().feed( &bytes ) {
+            return Err(Error::new( magnus::exception::runtime_error(), "Parser has already finished." ))
+        }
+
+        Ok(())
+    }
+
+    /// The document as parsed so far, it keeps growing until `finish`.
+    fn document( &self ) -> Node {
+        Node::from_handle( self.native.borrow().document() )
+    }
+
+    fn finish( &self ) -> Node {
+        Node::from_handle( self.native.borrow_mut().finish() )
+    }
+
+    fn is_finished( &self ) -> bool {
+        self.native.borrow().is_finished()
+    }
+}
+
+pub fn initialize() -> Result<(), Error> {
+    let scnr_ns = class::object().const_get::<_, RModule>("SCNR")?;
+    let engine_ns = scnr_ns.const_get::<_, RModule>("Engine")?;
+    let rust_ns = engine_ns.define_module("Rust")?;
+    let parser_ns = rust_ns.define_module("Parser")?;
+    let push_parser_class = parser_ns.define_class("PushParser", class::object())?;
+
+    push_parser_class.define_singleton_method("new", function!(PushParser::new, 2))?;
+
+    push_parser_class.define_method("<<", method!(PushParser::push, 1))?;
+    push_parser_class.define_method("document", method!(PushParser::document, 0))?;
+    push_parser_class.define_method("finish", method!(PushParser::finish, 0))?;
+    push_parser_class.define_method("finished?", method!(PushParser::is_finished, 0))?;
+
+    Ok(())
+}
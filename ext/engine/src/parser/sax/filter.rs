@@ -0,0 +1,200 @@
+//! Filter profiles, they decide which elements and text nodes the SAX handler keeps when
+//! filtering.
+//!
+//! Element rules are written as `name[attribute]...`, with one rule per alternative:
+//!
+//! * `[attribute]`          -- present and not empty.
+//! * `[attribute=value]`    -- equal to `value`.
+//! * `[attribute!=value]`   -- present and not equal to `value`.
+//! * `[attribute*=value]`   -- contains `value`.
+//!
+//! Values may be quoted and are compared case-insensitively.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use html5ever::{LocalName, Attribute};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operator {
+    NotEmpty,
+    Equals,
+    NotEquals,
+    Contains
+}
+
+#[derive(Debug, PartialEq)]
+struct Requirement {
+    attribute: LocalName,
+    operator:  Operator,
+    value:     String
+}
+
+impl Requirement {
+    fn matches( &self, attributes: &[Attribute] ) -> bool {
+        let value = match attributes.iter().find( |a| a.name.local == self.attribute ) {
+            Some(attribute) => attribute.value.to_lowercase(),
+            None            => return false
+        };
+
+        match self.operator {
+            Operator::NotEmpty  => !value.is_empty(),
+            Operator::Equals    => value == self.value,
+            Operator::NotEquals => value != self.value,
+            Operator::Contains  => value.contains( &self.value[..] )
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Profile {
+    /// Alternatives per element, each a set of requirements that must all hold.
+    elements:     HashMap<LocalName, Vec<Vec<Requirement>>>,
+
+    /// Elements whose text is kept.
+    text_parents: HashSet<LocalName>,
+
+    /// Whether to keep elements that have no rule.
+    others:       bool
+}
+
+impl Profile {
+    pub fn new( elements: &[String], text_parents: &[String], others: bool ) -> Result<Self, String> {
+        let mut profile = Profile {
+            others: others,
+            .. Default::default()
+        };
+
+        for rule in elements {
+            let (name, requirements) = parse_rule( rule ).map_err( |reason|
+                format!( "Invalid filter rule '{}': {}", rule, reason )
+            )?;
+
+            profile.elements.entry( name ).or_insert_with( Vec::new ).push( requirements );
+        }
+
+        for name in text_parents {
+            profile.text_parents.insert( LocalName::from( name.to_lowercase() ) );
+        }
+
+        Ok(profile)
+    }
+
+    pub fn allow_element( &self, name: &LocalName, attributes: &[Attribute] ) -> bool {
+        match self.elements.get( name ) {
+            Some(alternatives) => alternatives.iter().any( |requirements|
+                requirements.iter().all( |requirement| requirement.matches( attributes ) )
+            ),
+            None => self.others
+        }
+    }
+
+    pub fn allow_text( &self, parent_name: &LocalName ) -> bool {
+        self.text_parents.contains( parent_name )
+    }
+}
+
+fn parse_rule( rule: &str ) -> Result<(LocalName, Vec<Requirement>), String> {
+    let rule = rule.trim();
+
+    let name_end = rule.find( '[' ).unwrap_or( rule.len() );
+    let name     = rule[..name_end].trim().to_lowercase();
+    if name.is_empty() { return Err("expected an element name".to_string()) }
+
+    let mut requirements = vec![];
+    let mut rest         = &rule[name_end..];
+
+    while !rest.is_empty() {
+        if !rest.starts_with( '[' ) { return Err(format!( "unexpected '{}'", rest )) }
+
+        let end = match rest.find( ']' ) {
+            Some(end) => end,
+            None      => return Err("unterminated attribute".to_string())
+        };
+
+        requirements.push( parse_requirement( &rest[1..end] )? );
+        rest = rest[end + 1..].trim_start();
+    }
+
+    Ok((LocalName::from( name ), requirements))
+}
+
+fn parse_requirement( requirement: &str ) -> Result<Requirement, String> {
+    let (attribute, operator, value) =
+        if let Some(i) = requirement.find( "!=" ) {
+            (&requirement[..i], Operator::NotEquals, &requirement[i + 2..])
+        } else if let Some(i) = requirement.find( "*=" ) {
+            (&requirement[..i], Operator::Contains, &requirement[i + 2..])
+        } else if let Some(i) = requirement.find( '=' ) {
+            (&requirement[..i], Operator::Equals, &requirement[i + 1..])
+        } else {
+            (requirement, Operator::NotEmpty, "")
+        };
+
+    let attribute = attribute.trim().to_lowercase();
+    if attribute.is_empty() { return Err("expected an attribute name".to_string()) }
+
+    let value = value.trim().trim_matches( |c| c == '"' || c == '\'' ).to_lowercase();
+
+    Ok(Requirement {
+        attribute: LocalName::from( attribute ),
+        operator:  operator,
+        value:     value
+    })
+}
+
+fn compile( elements: &[&str], text_parents: &[&str], others: bool ) -> Arc<Profile> {
+    let elements     = elements.iter().map( |s| s.to_string() ).collect::<Vec<_>>();
+    let text_parents = text_parents.iter().map( |s| s.to_string() ).collect::<Vec<_>>();
+
+    Arc::new( Profile::new( &elements, &text_parents, others ).unwrap() )
+}
+
+lazy_static! {
+    /// Forms, inputs, links and cookie/refresh meta tags, everything else passes through.
+    static ref DEFAULT: Arc<Profile> = compile(
+        &[
+            "form", "input", "textarea", "option", "title", "script",
+            "frame[src]", "iframe[src]",
+            "a[href][href!=#]", "base[href][href!=#]", "area[href][href!=#]", "link[href][href!=#]",
+            "meta[http-equiv*=set-cookie]", "meta[http-equiv*=refresh]",
+            "select[name]", "select[id]", "button[name]", "button[id]", "button[formaction]"
+        ],
+        &[ "option", "textarea", "title", "script" ],
+        true
+    );
+
+    /// Elements that point to other resources.
+    static ref PATHS: Arc<Profile> = compile(
+        &[
+            "a[href][href!=#]", "area[href][href!=#]", "base[href][href!=#]", "link[href][href!=#]",
+            "frame[src]", "iframe[src]", "script[src]", "embed[src]", "audio[src]", "video[src]",
+            "img[src]", "img[srcset]", "source[src]", "source[srcset]",
+            "form[action]", "button[formaction]", "input[formaction]", "object[data]",
+            "meta[http-equiv*=refresh]"
+        ],
+        &[],
+        false
+    );
+
+    /// JSON-LD scripts and their contents.
+    static ref JSON_LD: Arc<Profile> = compile(
+        &[ "script[type=application/ld+json]" ],
+        &[ "script" ],
+        false
+    );
+}
+
+/// The profile used when filtering is simply turned on.
+pub fn default() -> Arc<Profile> {
+    DEFAULT.clone()
+}
+
+pub fn named( name: &str ) -> Option<Arc<Profile>> {
+    match name {
+        "default" => Some(DEFAULT.clone()),
+        "paths"   => Some(PATHS.clone()),
+        "json_ld" => Some(JSON_LD.clone()),
+        _         => None
+    }
+}
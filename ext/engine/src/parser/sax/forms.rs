@@ -0,0 +1,201 @@
+//! Form extraction, everything needed to submit each form of a document gathered in one pass.
+//!
+//! Fields belong to their closest ancestor form, unless a `form` attribute names another one by
+//! its ID, which is how fields outside of any form get to be submitted with it.
+
+use std::collections::HashMap;
+
+use html5ever::{LocalName, Attribute};
+
+use parser::sax::node::{Enum, Handle};
+
+const DEFAULT_METHOD:  &str = "get";
+const DEFAULT_ENCTYPE: &str = "application/x-www-form-urlencoded";
+
+#[derive(Debug, PartialEq)]
+pub struct SelectOption {
+    /// The `value` attribute, or the text when there isn't one.
+    pub value:    String,
+    pub text:     String,
+    pub selected: bool
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Field {
+    /// One of `input`, `select`, `textarea` or `button`.
+    pub tag:        LocalName,
+
+    pub name:       Option<String>,
+    pub id:         Option<String>,
+
+    /// Lowercase `type`, `select`/`textarea` for the elements that have none.
+    pub kind:       String,
+
+    /// Default value: the `value` attribute, the selected option or the textarea contents.
+    pub value:      String,
+
+    /// Whether checkboxes and radio buttons start out checked.
+    pub checked:    bool,
+
+    pub options:    Vec<SelectOption>,
+    pub attributes: Vec<(String, String)>
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Form {
+    /// As it appears in the markup, `None` if missing.
+    pub action:     Option<String>,
+
+    /// Lowercase, `get` if missing.
+    pub method:     String,
+
+    /// Lowercase, `application/x-www-form-urlencoded` if missing.
+    pub enctype:    String,
+
+    pub id:         Option<String>,
+    pub name:       Option<String>,
+    pub attributes: Vec<(String, String)>,
+    pub fields:     Vec<Field>
+}
+
+fn attribute( attributes: &[Attribute], name: &str ) -> Option<String> {
+    attributes.iter().
+        find( |a| &*a.name.local == name ).
+        map( |a| a.value.to_string() )
+}
+
+fn attribute_pairs( attributes: &[Attribute] ) -> Vec<(String, String)> {
+    attributes.iter().map( |a| (a.name.local.to_string(), a.value.to_string()) ).collect()
+}
+
+fn key( handle: &Handle ) -> usize {
+    &*handle.0 as *const _ as usize
+}
+
+fn is_field( name: &LocalName ) -> bool {
+    name == &local_name!("input") || name == &local_name!("select") ||
+        name == &local_name!("textarea") || name == &local_name!("button")
+}
+
+fn closest_form( handle: &Handle ) -> Option<Handle> {
+    let mut current = handle.borrow().parent.as_ref().and_then( |p| p.upgrade() ).map( Handle );
+
+    while let Some(ancestor) = current {
+        if let Enum::Element { ref name, .. } = ancestor.borrow().node {
+            if name == &local_name!("form") { return Some(ancestor.clone()) }
+        }
+
+        current = ancestor.borrow().parent.as_ref().and_then( |p| p.upgrade() ).map( Handle );
+    }
+
+    None
+}
+
+fn select_options( handle: &Handle ) -> Vec<SelectOption> {
+    let mut options = vec![];
+
+    handle.nodes_by_name( "option", |option| {
+        if let Enum::Element { ref attributes, .. } = option.borrow().node {
+            let text = option.text().trim().to_string();
+
+            options.push( SelectOption {
+                value:    attribute( attributes, "value" ).unwrap_or_else( || text.clone() ),
+                text:     text,
+                selected: attribute( attributes, "selected" ).is_some()
+            });
+        }
+    });
+
+    options
+}
+
+fn field( handle: &Handle, name: &LocalName, attributes: &[Attribute] ) -> Field {
+    let mut field = Field {
+        tag:        name.clone(),
+        name:       attribute( attributes, "name" ),
+        id:         attribute( attributes, "id" ),
+        kind:       String::new(),
+        value:      String::new(),
+        checked:    attribute( attributes, "checked" ).is_some(),
+        options:    vec![],
+        attributes: attribute_pairs( attributes )
+    };
+
+    if name == &local_name!("select") {
+        field.kind    = "select".to_string();
+        field.options = select_options( handle );
+
+        // Browsers go with the last selected option, or the first one.
+        field.value = field.options.iter().rev().find( |o| o.selected ).
+            or_else( || field.options.first() ).
+            map( |o| o.value.clone() ).
+            unwrap_or_default();
+
+    } else if name == &local_name!("textarea") {
+        field.kind  = "textarea".to_string();
+        field.value = handle.text();
+
+    } else {
+        let default = if name == &local_name!("button") { "submit" } else { "text" };
+
+        field.kind  = attribute( attributes, "type" ).
+            map( |kind| kind.to_lowercase() ).
+            unwrap_or_else( || default.to_string() );
+        field.value = attribute( attributes, "value" ).unwrap_or_default();
+    }
+
+    field
+}
+
+pub fn extract( root: &Handle ) -> Vec<Form> {
+    let mut forms   = vec![];
+    let mut by_node = HashMap::new();
+    let mut by_id   = HashMap::new();
+
+    root.nodes_by_name( "form", |handle| {
+        if let Enum::Element { ref attributes, .. } = handle.borrow().node {
+            let id = attribute( attributes, "id" );
+
+            if let Some(ref id) = id {
+                by_id.entry( id.clone() ).or_insert( forms.len() );
+            }
+            by_node.insert( key( handle ), forms.len() );
+
+            forms.push( Form {
+                action:     attribute( attributes, "action" ),
+                method:     attribute( attributes, "method" ).
+                    map( |method| method.to_lowercase() ).
+                    unwrap_or_else( || DEFAULT_METHOD.to_string() ),
+                enctype:    attribute( attributes, "enctype" ).
+                    map( |enctype| enctype.to_lowercase() ).
+                    unwrap_or_else( || DEFAULT_ENCTYPE.to_string() ),
+                id:         id,
+                name:       attribute( attributes, "name" ),
+                attributes: attribute_pairs( attributes ),
+                fields:     vec![]
+            });
+        }
+    });
+
+    if forms.is_empty() { return forms }
+
+    root.traverse( |handle| {
+        let borrowed = handle.borrow();
+
+        let (name, attributes) = match borrowed.node {
+            Enum::Element { ref name, ref attributes, .. } if is_field( name ) => (name, attributes),
+            _ => return
+        };
+
+        let owner = match attribute( attributes, "form" ) {
+            Some(id) => by_id.get( &id ).cloned(),
+            None     => closest_form( handle ).and_then( |form| by_node.get( &key( &form ) ).cloned() )
+        };
+
+        if let Some(index) = owner {
+            forms[index].fields.push( field( handle, name, attributes ) );
+        }
+    });
+
+    forms
+}
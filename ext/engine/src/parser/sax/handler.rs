@@ -1,16 +1,13 @@
 use std::rc::Rc;
+use std::sync::Arc;
 use std::collections::HashSet;
 
 use html5ever::{LocalName, Attribute};
-use parser::sax::node;
-
-const TEXT:       &str = "text";
-const ELEMENT:    &str = "element";
-const SET_COOKIE: &str = "set-cookie";
-const REFRESH:    &str = "refresh";
+use parser::sax::{node, filter};
+use parser::sax::position::Range;
 
 lazy_static! {
-    static ref SELF_CLOSE: HashSet<LocalName> = {
+    pub static ref SELF_CLOSE: HashSet<LocalName> = {
         let mut h = HashSet::new();
         h.insert( local_name!("area") );
         h.insert( local_name!("base") );
@@ -32,78 +29,13 @@ lazy_static! {
     };
 }
 
-fn allow( parent_name: &LocalName, kind: &str, name: &LocalName, attributes: &[Attribute] ) -> bool {
-    if kind == TEXT {
-        if parent_name == &local_name!("option") ||
-            parent_name == &local_name!("textarea") ||
-            parent_name == &local_name!("title") ||
-            parent_name == &local_name!("script") { return true }
-
-        return false
-    }
-
-    if kind == ELEMENT {
-        if name == &local_name!("form") || name == &local_name!("input") ||
-            name == &local_name!("textarea") || name == &local_name!("option") ||
-            name == &local_name!("title" ) || name == &local_name!("script" ) { return true }
-
-        if name == &local_name!("frame") || name == &local_name!("iframe") {
-            for attribute in attributes {
-                if attribute.name.local == local_name!( "src" ) {
-                    return !attribute.value.is_empty()
-                }
-            }
-
-            return false
-        }
-
-        if name == &local_name!("a") || name == &local_name!("base") || name == &local_name!("area") ||
-            name == &local_name!("link") {
-
-            for attribute in attributes {
-                if attribute.name.local == local_name!( "href" ) {
-                    return !attribute.value.is_empty() && attribute.value != format_tendril!("#")
-                }
-            }
-
-            return false
-        }
-
-        if name == &local_name!("meta") {
-            for attribute in attributes {
-                if attribute.name.local == local_name!( "http-equiv" ) {
-                    let value = attribute.value.to_lowercase();
-                    return value.contains( SET_COOKIE ) || value.contains( REFRESH )
-                }
-            }
-
-            return false
-        }
-
-        if name == &local_name!("select") || name == &local_name!("button") {
-            for attribute in attributes {
-                if (
-                    attribute.name.local == local_name!( "name" ) ||
-                    attribute.name.local == local_name!( "id"  )
-                ) && !attribute.value.is_empty() { return true }
-            }
-
-            return false
-        }
-
-        return true
-    }
-
-    false
-}
-
 pub struct Handler {
     current_node: node::Handle,
     skipped:      Vec<LocalName>,
-    filter:       bool
+    filter:       Option<Arc<filter::Profile>>
 }
 impl Handler {
-    pub fn new( root: node::Handle, filter: bool ) -> Self {
+    pub fn new( root: node::Handle, filter: Option<Arc<filter::Profile>> ) -> Self {
         Handler {
             current_node: root,
             skipped:      vec![],
@@ -114,20 +46,17 @@ impl Handler {
     pub fn start_element( &mut self,
         name:             LocalName,
         attributes:       Vec<Attribute>,
-        mut self_closing: bool
+        mut self_closing: bool,
+        position:         Option<Range>
     ) {
-        if self.filter {
+        if let Some(ref profile) = self.filter {
             match self.current_node.borrow().node {
-                node::Enum::Document => {
-                    if !allow( &local_name!(""), ELEMENT, &name, &attributes ) {
-                        self.skipped.push( name );
-                        return
-                    }
-                },
-
-                node::Enum::Element { name: ref pname, .. } => {
-                    if !allow( pname, ELEMENT, &name, &attributes ) {
-                        self.skipped.push( name );
+                node::Enum::Document | node::Enum::Element { .. } => {
+                    if !profile.allow_element( &name, &attributes ) {
+                        // Void elements get no end tag to pop them.
+                        if !self_closing && !SELF_CLOSE.contains( &name ) {
+                            self.skipped.push( name );
+                        }
                         return
                     }
                 },
@@ -140,23 +69,36 @@ impl Handler {
 
         let handle = node::Node::new_handle(
             node::Enum::Element { name: name, attributes: attributes, self_closing: self_closing },
-            Option::Some( Rc::downgrade( &self.current_node ) )
+            Option::Some( Rc::downgrade( &self.current_node ) ),
+            position
         );
 
         self.current_node.borrow_mut().children.push( handle.clone() );
         self.current_node = handle;
 
-        if self_closing { self.end_element( &local_name!("") ) }
+        if self_closing { self.end_element( &local_name!(""), None ) }
     }
 
-    pub fn end_element( &mut self, name: &LocalName, ) {
-        if self.filter && name != &local_name!("") &&
+    pub fn end_element( &mut self, name: &LocalName, position: Option<Range> ) {
+        if self.filter.is_some() && name != &local_name!("") &&
             !self.skipped.is_empty() && name == self.skipped.last().unwrap() {
             self.skipped.pop();
             return
         }
 
         let cloned      = self.current_node.clone();
+
+        if position.is_some() {
+            let mut borrowed = cloned.borrow_mut();
+            let closes = if let node::Enum::Element { name: ref current, .. } = borrowed.node {
+                current == name
+            } else {
+                false
+            };
+
+            if closes { borrowed.end_tag = position }
+        }
+
         let parent_ref  = &cloned.borrow().parent;
 
         if let Some( ref cloned_parent_ref ) = parent_ref.clone() {
@@ -164,15 +106,11 @@ impl Handler {
         }
     }
 
-    pub fn text( &mut self, text: String ) {
-        if self.filter {
+    pub fn text( &mut self, text: String, position: Option<Range> ) {
+        if let Some(ref profile) = self.filter {
             match self.current_node.borrow().node {
-                node::Enum::Document => {
-                    if !allow( &local_name!(""), TEXT, &local_name!( "" ), &[] ) { return }
-                },
-
                 node::Enum::Element { name: ref pname, .. } => {
-                    if !allow( pname, TEXT, &local_name!(""), &[] ) { return }
+                    if !profile.allow_text( pname ) { return }
                 },
 
                 _ => { return }
@@ -182,16 +120,18 @@ impl Handler {
         self.current_node.borrow_mut().children.push(
             node::Node::new_handle(
                 node::Enum::Text( text ),
-                Option::Some( Rc::downgrade( &self.current_node ) )
+                Option::Some( Rc::downgrade( &self.current_node ) ),
+                position
             )
         );
     }
 
-    pub fn comment( &mut self, text: String ) {
+    pub fn comment( &mut self, text: String, position: Option<Range> ) {
         self.current_node.borrow_mut().children.push(
             node::Node::new_handle(
                 node::Enum::Comment( text ),
-                Option::Some( Rc::downgrade( &self.current_node ) )
+                Option::Some( Rc::downgrade( &self.current_node ) ),
+                position
             )
         );
     }
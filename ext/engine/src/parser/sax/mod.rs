@@ -1,3 +1,10 @@
 pub mod parser;
+pub mod tree_builder;
 pub mod handler;
+pub mod filter;
 pub mod node;
+pub mod selector;
+pub mod xpath;
+pub mod position;
+pub mod forms;
+pub mod paths;
@@ -5,6 +5,9 @@ use std::cell::RefCell;
 
 use html5ever::{LocalName, Attribute};
 
+use parser::sax::selector::SelectorList;
+use parser::sax::position::{Position, Range};
+
 pub type WeakHandle = Weak<RefCell<Node>>;
 
 const TO_HTML_DOCTYPE:        &str = "<!DOCTYPE html>";
@@ -129,6 +132,29 @@ impl Handle {
         })
     }
 
+    pub fn css<F>( &self, selectors: &SelectorList, mut cb: F ) where F: FnMut( &Handle ) {
+        self.traverse( |handle| {
+            if selectors.matches( handle, self ) { cb( handle ) }
+        })
+    }
+
+    /// First descendant, in document order, that matches `selectors`.
+    pub fn at_css( &self, selectors: &SelectorList ) -> Option<Handle> {
+        Handle::finder( &self.borrow().children, &|handle| selectors.matches( handle, self ) )
+    }
+
+    fn finder<F>( children: &[Handle], predicate: &F ) -> Option<Handle> where F: Fn( &Handle ) -> bool {
+        for handle in children {
+            if predicate( handle ) { return Some(handle.clone()) }
+
+            if let Some(found) = Handle::finder( &handle.borrow().children, predicate ) {
+                return Some(found)
+            }
+        }
+
+        None
+    }
+
     pub fn text( &self ) -> String {
         let borrowed = &self.borrow();
 
@@ -145,6 +171,26 @@ impl Handle {
         }
     }
 
+    /// Where the node starts in the source.
+    pub fn position( &self ) -> Option<Position> {
+        self.borrow().position.map( |range| range.start )
+    }
+
+    /// All of the node's markup, up to its end tag or, when that's implied, its last descendant.
+    pub fn source_range( &self ) -> Option<Range> {
+        let borrowed = self.borrow();
+        let range    = borrowed.position?;
+
+        let end = match borrowed.end_tag {
+            Some(end_tag) => end_tag.end,
+            None          => borrowed.children.iter().rev()
+                .filter_map( |child| child.source_range() ).next()
+                .map_or( range.end, |last| if last.end.offset > range.end.offset { last.end } else { range.end } )
+        };
+
+        Some(Range { start: range.start, end: end })
+    }
+
     pub fn traverse_comments<F>( &self, mut cb: F ) where F: FnMut( &Handle ) {
         self.traverse( |handle| {
             if let Enum::Comment( .. ) = handle.borrow().node {
@@ -185,18 +231,27 @@ pub enum Enum {
 pub struct Node {
     pub node:     Enum,
     pub parent:   Option<WeakHandle>,
-    pub children: Vec<Handle>
+    pub children: Vec<Handle>,
+
+    /// Where the node appears in the source, the start tag for elements.
+    pub position: Option<Range>,
+
+    /// Where the end tag of an element appears, if it has one.
+    pub end_tag:  Option<Range>
 }
 
 impl Node {
     pub fn new_handle(
-        node:   Enum,
-        parent: Option<WeakHandle>
+        node:     Enum,
+        parent:   Option<WeakHandle>,
+        position: Option<Range>
     ) -> Handle {
         Handle( Rc::new( RefCell::new( Node {
             node:     node,
             parent:   parent,
-            children: vec![]
+            children: vec![],
+            position: position,
+            end_tag:  None
         })))
     }
 }
@@ -1,14 +1,60 @@
+use std::str;
 use std::default::Default;
+use std::sync::Arc;
 
-use tendril::ByteTendril;
+use tendril::StrTendril;
 
 use html5ever::tokenizer::{BufferQueue, TokenSink, Tokenizer, Token, TokenizerOpts, TokenSinkResult};
 use html5ever::tokenizer::{CharacterTokens, NullCharacterToken, TagToken, StartTag, EndTag, CommentToken};
 
-use parser::sax::{handler, node};
+use parser::sax::{handler, node, filter, position};
+
+const REPLACEMENT_CHARACTER: &str = "\u{FFFD}";
 
 struct SAX {
-    pub handler: handler::Handler
+    pub handler: handler::Handler,
+
+    /// Only there if positions are tracked.
+    pub locator: Option<position::Locator>,
+
+    /// Last run of characters, held back in case the next chunk continues it, along with where
+    /// it came from.
+    text:        Option<(String, Option<(usize, usize)>)>,
+
+    /// Whether a chunk ended since `text` was buffered.
+    chunk_ended: bool
+}
+
+impl SAX {
+    fn new( handler: handler::Handler, positions: bool ) -> Self {
+        SAX {
+            handler:     handler,
+            locator:     if positions { Some(position::Locator::new()) } else { None },
+            text:        None,
+            chunk_ended: false
+        }
+    }
+
+    fn flush_text( &mut self ) {
+        if let Some((text, span)) = self.text.take() {
+            let sanitized = text.trim().to_string();
+            if sanitized.is_empty() { return }
+
+            let range = match (span, self.locator.as_mut()) {
+                (Some((start, end)), Some(locator)) => {
+                    let (leading, trailing) = {
+                        let raw = locator.slice( start, end );
+                        (raw.len() - raw.trim_start().len(), raw.len() - raw.trim_end().len())
+                    };
+
+                    Some(locator.range( start + leading, end - trailing ))
+                },
+                _ => None
+            };
+
+            self.handler.text( sanitized, range )
+        }
+    }
 }
 
 impl TokenSink for SAX {
@@ -16,35 +62,61 @@ impl TokenSink for SAX {
 
     fn process_token( &mut self, token: Token, _: u64) -> TokenSinkResult<()> {
 
-        match token {
+        if let CharacterTokens( text ) = token {
+            let span = self.locator.as_mut().and_then( |l| l.characters( &text ) );
 
-            CharacterTokens( text ) => {
-                let sanitized = text.chars().collect::<String>().trim().to_string();
-                if sanitized .is_empty() { return TokenSinkResult::Continue }
+            match self.text {
+                Some((ref mut buffered, ref mut buffered_span)) if self.chunk_ended => {
+                    buffered.push_str( &text );
 
-                self.handler.text( sanitized  )
+                    *buffered_span = match (*buffered_span, span) {
+                        (Some((start, _)), Some((_, end))) => Some((start, end)),
+                        _                                  => None
+                    };
+                },
+                _ => {
+                    self.flush_text();
+                    self.text = Some((text.chars().collect::<String>(), span));
+                }
             }
 
+            self.chunk_ended = false;
+            return TokenSinkResult::Continue
+        }
+
+        // Locate first, the text before the token ends where the token starts.
+        let located = self.locator.as_mut().and_then( |l| l.locate( &token ) );
+
+        self.flush_text();
+        self.chunk_ended = false;
+
+        let range = match (located, self.locator.as_mut()) {
+            (Some((start, end)), Some(locator)) => Some(locator.range( start, end )),
+            _                                   => None
+        };
+
+        match token {
+
             CommentToken( comment ) => {
                 let sanitized = comment.chars().collect::<String>().trim().to_string();
                 if sanitized .is_empty() { return TokenSinkResult::Continue }
 
-                self.handler.comment( sanitized  )
+                self.handler.comment( sanitized, range )
             },
 
             NullCharacterToken => {
-                self.handler.text( "\0".to_string() )
+                self.handler.text( "\0".to_string(), range )
             },
 
             TagToken( tag ) => {
                 match tag.kind {
                     StartTag => {
-                        self.handler.start_element( tag.name, tag.attrs, tag.self_closing );
+                        self.handler.start_element( tag.name, tag.attrs, tag.self_closing, range );
                     },
 
                     EndTag   => {
                         if !tag.self_closing {
-                            self.handler.end_element( &tag.name )
+                            self.handler.end_element( &tag.name, range )
                         }
                     }
                 }
@@ -58,29 +130,146 @@ impl TokenSink for SAX {
 
         TokenSinkResult::Continue
     }
+
+    fn end( &mut self ) {
+        self.flush_text()
+    }
+}
+
+/// Decodes as much of `bytes` as possible, leaving an incomplete trailing UTF-8 sequence in
+/// place for the next chunk to finish; invalid sequences become U+FFFD.
+fn drain_utf8( bytes: &mut Vec<u8> ) -> String {
+    let mut decoded = String::with_capacity( bytes.len() );
+    let mut start   = 0;
+
+    loop {
+        match str::from_utf8( &bytes[start..] ) {
+            Ok(valid) => {
+                decoded.push_str( valid );
+                start = bytes.len();
+                break
+            },
+
+            Err(e) => {
+                let valid_up_to = start + e.valid_up_to();
+                decoded.push_str( &String::from_utf8_lossy( &bytes[start..valid_up_to] ) );
+
+                match e.error_len() {
+                    Some(length) => {
+                        decoded.push_str( REPLACEMENT_CHARACTER );
+                        start = valid_up_to + length;
+                    },
+
+                    // Incomplete, wait for more data.
+                    None => {
+                        start = valid_up_to;
+                        break
+                    }
+                }
+            }
+        }
+    }
+
+    bytes.drain( ..start );
+    decoded
 }
 
-pub fn parse( html: &str, filter: bool ) -> node::Handle {
-    let root    = node::Node::new_handle( node::Enum::Document, None );
-    let handler = handler::Handler::new( root.clone(), filter );
-    let sink    = SAX { handler: handler };
+/// Incremental parser, the document is built as chunks come in.
+pub struct PushParser {
+    tokenizer: Tokenizer<SAX>,
+    input:     BufferQueue,
+    root:      node::Handle,
+
+    /// Bytes of a UTF-8 sequence split across chunks.
+    pending:   Vec<u8>,
+    finished:  bool
+}
 
-    let mut chunk = ByteTendril::new();
-    let _ = chunk.try_push_bytes( html.as_bytes());
+impl PushParser {
 
-    let mut input = BufferQueue::new();
+    /// Node positions are only tracked if `positions` is set, it costs a copy of the source and
+    /// locating every token in it.
+    pub fn new( filter: Option<Arc<filter::Profile>>, positions: bool ) -> Self {
+        let root    = node::Node::new_handle( node::Enum::Document, None, None );
+        let handler = handler::Handler::new( root.clone(), filter );
+        let sink    = SAX::new( handler, positions );
 
-    match chunk.try_reinterpret() {
-        Result::Ok( val ) => input.push_back( val ),
-        Result::Err( _err ) =>
-            return root
+        PushParser {
+            tokenizer: Tokenizer::new( sink, TokenizerOpts { .. Default::default() }),
+            input:     BufferQueue::new(),
+            root:      root,
+            pending:   vec![],
+            finished:  false
+        }
+    }
+
+    /// Parses a chunk of bytes, which may end in the middle of a character.
+    ///
+    /// Returns `false` if the parser has already finished.
+    pub fn feed( &mut self, chunk: &[u8] ) -> bool {
+        if self.finished { return false }
+
+        self.pending.extend_from_slice( chunk );
+        let decoded = drain_utf8( &mut self.pending );
+
+        self.feed_str( &decoded );
+        true
     }
 
-    let mut tok = Tokenizer::new( sink, TokenizerOpts { .. Default::default() });
+    fn feed_str( &mut self, chunk: &str ) {
+        if chunk.is_empty() { return }
+
+        if let Some(ref mut locator) = self.tokenizer.sink.locator {
+            locator.push( chunk );
+        }
+
+        self.input.push_back( StrTendril::from_slice( chunk ) );
 
-    let _ = tok.feed( &mut input );
-    assert!( input.is_empty() );
-    tok.end();
+        let _ = self.tokenizer.feed( &mut self.input );
+        assert!( self.input.is_empty() );
+
+        let sink = &mut self.tokenizer.sink;
+        sink.chunk_ended = true;
+
+        if let Some(ref mut locator) = sink.locator {
+            // Held back text still needs its source.
+            let keep = match sink.text {
+                Some((_, Some((start, _)))) => start,
+                _                           => locator.end()
+            };
+            locator.discard( keep );
+        }
+    }
+
+    /// Flushes whatever is left and returns the document.
+    pub fn finish( &mut self ) -> node::Handle {
+        if !self.finished {
+            self.finished = true;
+
+            if !self.pending.is_empty() {
+                let rest = String::from_utf8_lossy( &self.pending ).into_owned();
+                self.pending.clear();
+                self.feed_str( &rest );
+            }
+
+            self.tokenizer.end();
+        }
+
+        self.root.clone()
+    }
+
+    pub fn is_finished( &self ) -> bool {
+        self.finished
+    }
+
+    /// The document as parsed so far.
+    pub fn document( &self ) -> node::Handle {
+        self.root.clone()
+    }
+}
 
-    root
+pub fn parse( html: &str, filter: Option<Arc<filter::Profile>>, positions: bool ) -> node::Handle {
+    let mut parser = PushParser::new( filter, positions );
+    parser.feed_str( html );
+    parser.finish()
 }
@@ -0,0 +1,263 @@
+//! Path extraction, every path a document points to gathered in one pass.
+//!
+//! Covers what the `path_extractors` components look for, each path tagged with the component
+//! that would have found it, along with the attributes they don't look at. `data_url` is left
+//! to its component, it scans the raw body.
+
+use std::collections::HashSet;
+
+use html5ever::{LocalName, Attribute};
+
+use parser::sax::node::{Enum, Handle};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Extractor {
+    Anchors,
+    Areas,
+    Base,
+    Comments,
+    Forms,
+    Frames,
+    Links,
+    MetaRefresh,
+    Scripts,
+
+    Srcset,
+    FormAction,
+    Poster,
+    Data,
+    Ping,
+    Manifest
+}
+
+impl Extractor {
+    /// Same as the name of the matching `path_extractors` component, where there is one.
+    pub fn name( &self ) -> &'static str {
+        match *self {
+            Extractor::Anchors     => "anchors",
+            Extractor::Areas       => "areas",
+            Extractor::Base        => "base",
+            Extractor::Comments    => "comments",
+            Extractor::Forms       => "forms",
+            Extractor::Frames      => "frames",
+            Extractor::Links       => "links",
+            Extractor::MetaRefresh => "meta_refresh",
+            Extractor::Scripts     => "scripts",
+
+            Extractor::Srcset      => "srcset",
+            Extractor::FormAction  => "formaction",
+            Extractor::Poster      => "poster",
+            Extractor::Data        => "data",
+            Extractor::Ping        => "ping",
+            Extractor::Manifest    => "manifest"
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Path {
+    /// As it appears in the markup, not resolved against anything.
+    pub path:      String,
+    pub extractor: Extractor
+}
+
+fn attribute<'a>( attributes: &'a [Attribute], name: &str ) -> Option<&'a str> {
+    attributes.iter().
+        find( |a| &*a.name.local == name ).
+        map( |a| &*a.value )
+}
+
+fn is_path_character( c: char ) -> bool {
+    c.is_ascii_alphanumeric() || c == '/' || c == '%' || c == '.' || c == '_' || c == '-'
+}
+
+/// Runs of path characters that look like absolute paths to files, as scripts mention them.
+fn script_paths( text: &str ) -> Vec<String> {
+    let mut paths: Vec<String> = vec![];
+
+    for candidate in text.split( |c| !is_path_character( c ) ) {
+        if !candidate.starts_with( '/' ) || candidate.starts_with( "//" ) ||
+            !candidate.contains( '.' ) { continue }
+
+        if !paths.iter().any( |path| path == candidate ) {
+            paths.push( candidate.to_string() );
+        }
+    }
+
+    paths
+}
+
+/// Paths at the start of the text or right after whitespace, a lone `/` doesn't count.
+fn comment_paths( text: &str ) -> Vec<String> {
+    let mut paths    = vec![];
+    let mut previous = None;
+
+    for (i, c) in text.char_indices() {
+        let at_boundary = previous.map_or( true, |p: char| p.is_ascii_whitespace() );
+        previous = Some(c);
+
+        if c != '/' || !at_boundary { continue }
+
+        let end = text[i..].find( |c| !is_path_character( c ) ).map_or( text.len(), |end| i + end );
+        if end - i < 2 { continue }
+
+        paths.push( text[i..end].to_string() );
+    }
+
+    paths
+}
+
+/// The URL in `content="5; url='/path'"`.
+fn meta_refresh_path( content: &str ) -> Option<String> {
+    let url = content.splitn( 2, ';' ).nth( 1 )?;
+    let url = url.splitn( 2, '=' ).last()?.trim();
+    if url.is_empty() { return None }
+
+    for quote in &[ '\'', '"' ] {
+        if url.len() > 1 && url.starts_with( *quote ) && url.ends_with( *quote ) {
+            return Some(url[1..url.len() - 1].to_string())
+        }
+    }
+
+    Some(url.to_string())
+}
+
+/// Candidate URLs, leaving out their width and density descriptors.
+fn srcset_paths( srcset: &str ) -> Vec<String> {
+    let mut paths = vec![];
+    let mut rest  = srcset;
+
+    loop {
+        rest = rest.trim_start_matches( |c: char| c.is_ascii_whitespace() || c == ',' );
+        if rest.is_empty() { break }
+
+        let end = rest.find( |c: char| c.is_ascii_whitespace() ).unwrap_or( rest.len() );
+        let url = &rest[..end];
+        rest    = &rest[end..];
+
+        // A trailing comma means there are no descriptors.
+        if url.ends_with( ',' ) {
+            paths.push( url.trim_end_matches( ',' ).to_string() );
+            continue
+        }
+        paths.push( url.to_string() );
+
+        let mut depth = 0;
+        let mut end   = rest.len();
+        for (i, c) in rest.char_indices() {
+            match c {
+                '('               => depth += 1,
+                ')' if depth > 0  => depth -= 1,
+                ',' if depth == 0 => {
+                    end = i;
+                    break
+                },
+                _ => {}
+            }
+        }
+        rest = &rest[end..];
+    }
+
+    paths
+}
+
+fn element( handle: &Handle, name: &LocalName, attributes: &[Attribute], paths: &mut Vec<Path> ) {
+    {
+        let mut push = |path: &str, extractor| {
+            paths.push( Path {
+                path:      path.to_string(),
+                extractor: extractor
+            });
+        };
+
+        let tagged = match *name {
+            local_name!("a")      => Some(("href", Extractor::Anchors)),
+            local_name!("area")   => Some(("href", Extractor::Areas)),
+            local_name!("base")   => Some(("href", Extractor::Base)),
+            local_name!("link")   => Some(("href", Extractor::Links)),
+            local_name!("form")   => Some(("action", Extractor::Forms)),
+            local_name!("frame")  |
+            local_name!("iframe") => Some(("src", Extractor::Frames)),
+            local_name!("script") => Some(("src", Extractor::Scripts)),
+            local_name!("object") => Some(("data", Extractor::Data)),
+            local_name!("video")  => Some(("poster", Extractor::Poster)),
+            local_name!("html")   => Some(("manifest", Extractor::Manifest)),
+            _                     => None
+        };
+
+        if let Some((attribute_name, extractor)) = tagged {
+            if let Some(path) = attribute( attributes, attribute_name ) {
+                push( path, extractor );
+            }
+        }
+
+        if *name == local_name!("a") || *name == local_name!("area") {
+            if let Some(ping) = attribute( attributes, "ping" ) {
+                for path in ping.split_whitespace() {
+                    push( path, Extractor::Ping );
+                }
+            }
+        }
+
+        if *name == local_name!("img") || *name == local_name!("source") {
+            if let Some(srcset) = attribute( attributes, "srcset" ) {
+                for path in srcset_paths( srcset ) {
+                    push( &path, Extractor::Srcset );
+                }
+            }
+        }
+
+        if *name == local_name!("button") || *name == local_name!("input") {
+            if let Some(path) = attribute( attributes, "formaction" ) {
+                push( path, Extractor::FormAction );
+            }
+        }
+
+        let is_refresh = attribute( attributes, "http-equiv" ).
+            map_or( false, |value| value.eq_ignore_ascii_case( "refresh" ) );
+
+        if is_refresh {
+            if let Some(path) = attribute( attributes, "content" ).and_then( meta_refresh_path ) {
+                push( &path, Extractor::MetaRefresh );
+            }
+        }
+    }
+
+    if *name == local_name!("script") {
+        for path in script_paths( &handle.text() ) {
+            paths.push( Path {
+                path:      path,
+                extractor: Extractor::Scripts
+            });
+        }
+    }
+}
+
+pub fn extract( root: &Handle ) -> Vec<Path> {
+    let mut paths = vec![];
+
+    root.traverse( |handle| {
+        match handle.borrow().node {
+            Enum::Element { ref name, ref attributes, .. } => {
+                element( handle, name, attributes, &mut paths )
+            },
+
+            Enum::Comment( ref text ) => {
+                for path in comment_paths( text ) {
+                    paths.push( Path {
+                        path:      path,
+                        extractor: Extractor::Comments
+                    });
+                }
+            },
+
+            _ => {}
+        }
+    });
+
+    // Once per extractor, like the components' results would be once merged.
+    let mut seen = HashSet::new();
+    paths.retain( |path| seen.insert( (path.path.clone(), path.extractor) ) );
+
+    paths
+}
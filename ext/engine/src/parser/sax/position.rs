@@ -0,0 +1,313 @@
+//! Source positions, tokens are traced back to the markup they came from.
+//!
+//! The tokenizer doesn't report where tokens start or end, so the `Locator` keeps its own copy
+//! of the source and follows along, searching for each piece of markup from where the previous
+//! one ended and matching character runs in place.
+
+use html5ever::tokenizer::{Token, TagToken, StartTag, EndTag, CommentToken, DoctypeToken, NullCharacterToken};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Position {
+    /// Byte offset into the source.
+    pub offset: usize,
+
+    /// Starts at 1.
+    pub line:   usize,
+
+    /// Starts at 1, counted in characters.
+    pub column: usize
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Position {
+            offset: 0,
+            line:   1,
+            column: 1
+        }
+    }
+}
+
+/// From the first byte of a node's markup up to, but not including, the byte after its last.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Range {
+    pub start: Position,
+    pub end:   Position
+}
+
+#[derive(Default)]
+pub struct Locator {
+    /// Source not yet discarded, starting at offset `base`.
+    source: String,
+    base:   usize,
+
+    /// Where the previous token ended.
+    cursor: usize,
+
+    /// Whether a character run couldn't be matched, until the next markup puts us back on track.
+    lost:   bool,
+
+    /// Last resolved position, resolving only ever moves forward from it.
+    last:   Position
+}
+
+impl Locator {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn push( &mut self, chunk: &str ) {
+        self.source.push_str( chunk );
+    }
+
+    /// Offset right after the last byte pushed so far.
+    pub fn end( &self ) -> usize {
+        self.base + self.source.len()
+    }
+
+    pub fn slice( &self, start: usize, end: usize ) -> &str {
+        &self.source[start - self.base..end - self.base]
+    }
+
+    fn rest( &self ) -> &str {
+        &self.source[self.cursor - self.base..]
+    }
+
+    /// Drops source that's no longer needed, everything before `offset` and the last resolved
+    /// position.
+    pub fn discard( &mut self, offset: usize ) {
+        let until = offset.min( self.last.offset ).min( self.cursor );
+        if until <= self.base { return }
+
+        self.source.drain( ..until - self.base );
+        self.base = until;
+    }
+
+    pub fn resolve( &mut self, offset: usize ) -> Position {
+        if offset <= self.last.offset { return self.last }
+
+        let mut position = self.last;
+        for c in self.slice( self.last.offset, offset ).chars() {
+            if c == '\n' {
+                position.line  += 1;
+                position.column = 1;
+            } else {
+                position.column += 1;
+            }
+        }
+
+        position.offset = offset;
+        self.last       = position;
+        position
+    }
+
+    pub fn range( &mut self, start: usize, end: usize ) -> Range {
+        Range {
+            start: self.resolve( start ),
+            end:   self.resolve( end )
+        }
+    }
+
+    fn found( &mut self, start: usize, length: usize ) -> Option<(usize, usize)> {
+        let start = self.cursor + start;
+
+        self.cursor = start + length;
+        self.lost   = false;
+
+        Some((start, self.cursor))
+    }
+
+    pub fn start_tag( &mut self, name: &str ) -> Option<(usize, usize)> {
+        self.tag( "<", name )
+    }
+
+    pub fn end_tag( &mut self, name: &str ) -> Option<(usize, usize)> {
+        self.tag( "</", name )
+    }
+
+    fn tag( &mut self, open: &str, name: &str ) -> Option<(usize, usize)> {
+        let found = {
+            let rest = self.rest();
+
+            rest.match_indices( open ).filter_map( |(i, _)| {
+                let after = &rest.as_bytes()[i + open.len()..];
+                if after.len() < name.len() ||
+                    !after[..name.len()].eq_ignore_ascii_case( name.as_bytes() ) { return None }
+
+                match after.get( name.len() ) {
+                    Some(&c) if c != b'/' && c != b'>' && !c.is_ascii_whitespace() => None,
+                    _ => {
+                        let name_end = i + open.len() + name.len();
+                        tag_end( &rest[name_end..] ).map( |length| (i, name_end + length - i) )
+                    }
+                }
+            }).next()
+        };
+
+        let (start, length) = found?;
+        self.found( start, length )
+    }
+
+    /// Where the markup of `token` is in the source, if it has any; character runs go through
+    /// `characters`.
+    pub fn locate( &mut self, token: &Token ) -> Option<(usize, usize)> {
+        match *token {
+            TagToken( ref tag ) => match tag.kind {
+                StartTag => self.start_tag( &tag.name ),
+                EndTag   => self.end_tag( &tag.name )
+            },
+
+            CommentToken( _ ) | DoctypeToken( _ ) => self.markup(),
+            NullCharacterToken                    => self.characters( "\0" ),
+
+            _ => None
+        }
+    }
+
+    /// Comments, doctypes and processing instructions.
+    pub fn markup( &mut self ) -> Option<(usize, usize)> {
+        let found = {
+            let rest = self.rest();
+
+            rest.match_indices( '<' ).filter_map( |(i, _)| {
+                let after = &rest[i + 1..];
+
+                let length = if after.starts_with( "!--" ) {
+                    after[1..].find( "-->" ).map( |j| j + 5 ).unwrap_or( after.len() + 1 )
+                } else if after.starts_with( '!' ) || after.starts_with( '?' ) ||
+                    ( after.starts_with( '/' ) &&
+                        !after[1..].starts_with( |c: char| c.is_ascii_alphabetic() ) ) {
+                    after.find( '>' ).map( |j| j + 2 ).unwrap_or( after.len() + 1 )
+                } else {
+                    return None
+                };
+
+                Some((i, length))
+            }).next()
+        };
+
+        let (start, length) = found?;
+        self.found( start, length )
+    }
+
+    /// Matches a run of characters right where the previous token ended.
+    pub fn characters( &mut self, text: &str ) -> Option<(usize, usize)> {
+        if self.lost { return None }
+
+        let length = {
+            let rest = self.rest();
+
+            if rest.starts_with( '&' ) && ( !text.starts_with( '&' ) || is_ampersand_reference( rest ) ) {
+                character_reference( rest )
+            } else {
+                literal( rest, text )
+            }
+        };
+
+        match length {
+            Some(length) => self.found( 0, length ),
+            None         => {
+                self.lost = true;
+                None
+            }
+        }
+    }
+}
+
+/// Line and column lookups at any offset, for when the whole source is kept around.
+pub struct Lines<'a> {
+    source: &'a str,
+    starts: Vec<usize>
+}
+
+impl<'a> Lines<'a> {
+    pub fn new( source: &'a str ) -> Self {
+        let mut starts = vec![0];
+        starts.extend( source.match_indices( '\n' ).map( |(i, _)| i + 1 ) );
+
+        Lines {
+            source: source,
+            starts: starts
+        }
+    }
+
+    pub fn position( &self, offset: usize ) -> Position {
+        let line = match self.starts.binary_search( &offset ) {
+            Ok(line)  => line,
+            Err(line) => line - 1
+        };
+
+        Position {
+            offset: offset,
+            line:   line + 1,
+            column: self.source[self.starts[line]..offset].chars().count() + 1
+        }
+    }
+}
+
+/// Length up to and including the `>` that closes a tag, skipping quoted attribute values.
+fn tag_end( rest: &str ) -> Option<usize> {
+    let bytes            = rest.as_bytes();
+    let mut after_equals = false;
+    let mut i            = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'>' => return Some(i + 1),
+            b'=' => after_equals = true,
+
+            quote @ b'"' | quote @ b'\'' if after_equals => {
+                i += 1 + rest[i + 1..].find( quote as char )?;
+                after_equals = false;
+            },
+
+            c if c.is_ascii_whitespace() => {},
+            _ => after_equals = false
+        }
+
+        i += 1;
+    }
+
+    None
+}
+
+/// Length of the source `text` was decoded from, taking newline normalization into account.
+fn literal( rest: &str, text: &str ) -> Option<usize> {
+    let bytes  = rest.as_bytes();
+    let mut at = 0;
+
+    for c in text.chars() {
+        if c == '\n' && bytes.get( at ) == Some(&b'\r') {
+            at += if bytes.get( at + 1 ) == Some(&b'\n') { 2 } else { 1 };
+            continue
+        }
+
+        if !rest[at..].starts_with( c ) { return None }
+        at += c.len_utf8();
+    }
+
+    Some(at)
+}
+
+fn character_reference( rest: &str ) -> Option<usize> {
+    let bytes = rest.as_bytes();
+
+    let (digits_start, is_digit): (usize, fn( &u8 ) -> bool) =
+        match (bytes.get( 1 ), bytes.get( 2 )) {
+            (Some(&b'#'), Some(&b'x')) | (Some(&b'#'), Some(&b'X')) => (3, u8::is_ascii_hexdigit),
+            (Some(&b'#'), _) => (2, u8::is_ascii_digit),
+            _                => (1, u8::is_ascii_alphanumeric)
+        };
+
+    let length = digits_start +
+        bytes[digits_start..].iter().take_while( |c| is_digit( c ) ).count();
+
+    if length == digits_start { return None }
+
+    Some(if bytes.get( length ) == Some(&b';') { length + 1 } else { length })
+}
+
+fn is_ampersand_reference( rest: &str ) -> bool {
+    let lowercase = rest.as_bytes()[..rest.len().min( 5 )].to_ascii_lowercase();
+    lowercase.starts_with( b"&amp" ) || lowercase.starts_with( b"&#38" ) || lowercase.starts_with( b"&#x26" )
+}
@@ -0,0 +1,491 @@
+//! CSS selectors for `node::Handle` trees.
+//!
+//! Supports type, universal, ID, class and attribute selectors (`=`, `~=`, `|=`, `^=`, `$=`,
+//! `*=`, with an optional `i` flag), the descendant, `>`, `+` and `~` combinators, `:not()`,
+//! `:nth-child()`, `:nth-last-child()`, `:first-child` and `:last-child`.
+
+use std::rc::Rc;
+
+use html5ever::Attribute;
+
+use parser::sax::node::{Enum, Handle};
+
+const CLASS: &str = "class";
+const ID:    &str = "id";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Combinator {
+    Descendant,
+    Child,
+    NextSibling,
+    SubsequentSibling
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operator {
+    Exists,
+    Equals,
+    Includes,
+    DashMatch,
+    Prefix,
+    Suffix,
+    Substring
+}
+
+#[derive(Debug, PartialEq)]
+enum Condition {
+    Id( String ),
+    Class( String ),
+    Attribute { name: String, operator: Operator, value: String, case_insensitive: bool },
+    Not( SelectorList ),
+
+    /// `an+b`, counting from the end when `last` is set.
+    NthChild { a: i64, b: i64, last: bool }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Compound {
+    name:       Option<String>,
+    conditions: Vec<Condition>
+}
+
+/// Compounds from left to right, each with the combinator that links it to the previous one.
+#[derive(Debug, PartialEq)]
+struct Complex( Vec<(Combinator, Compound)> );
+
+/// A comma-separated group of selectors.
+#[derive(Debug, PartialEq)]
+pub struct SelectorList( Vec<Complex> );
+
+struct Parser {
+    chars:    Vec<char>,
+    position: usize
+}
+
+impl Parser {
+
+    fn peek( &self ) -> Option<char> {
+        self.chars.get( self.position ).cloned()
+    }
+
+    fn next( &mut self ) -> Option<char> {
+        let c = self.peek();
+        if c.is_some() { self.position += 1 }
+        c
+    }
+
+    fn eat( &mut self, c: char ) -> bool {
+        if self.peek() != Some(c) { return false }
+        self.position += 1;
+        true
+    }
+
+    fn expect( &mut self, c: char ) -> Result<(), String> {
+        if self.eat( c ) { return Ok(()) }
+        Err(self.error( &format!( "expected '{}'", c ) ))
+    }
+
+    /// Skips whitespace, returns whether there was any.
+    fn skip_whitespace( &mut self ) -> bool {
+        let start = self.position;
+        while self.peek().map_or( false, char::is_whitespace ) { self.position += 1 }
+        self.position > start
+    }
+
+    fn error( &self, reason: &str ) -> String {
+        match self.peek() {
+            Some(c) => format!( "{} at '{}', position {}", reason, c, self.position ),
+            None    => format!( "{} at the end", reason )
+        }
+    }
+
+    fn is_identifier_char( c: char ) -> bool {
+        c.is_alphanumeric() || c == '-' || c == '_' || !c.is_ascii()
+    }
+
+    fn identifier( &mut self ) -> Result<String, String> {
+        let mut identifier = String::new();
+
+        loop {
+            match self.peek() {
+                Some('\\') => {
+                    self.position += 1;
+                    identifier.push( self.next().ok_or_else( || self.error( "unfinished escape" ) )? );
+                },
+                Some(c) if Parser::is_identifier_char( c ) => {
+                    self.position += 1;
+                    identifier.push( c );
+                },
+                _ => break
+            }
+        }
+
+        if identifier.is_empty() { return Err(self.error( "expected an identifier" )) }
+        Ok(identifier)
+    }
+
+    fn string( &mut self, quote: char ) -> Result<String, String> {
+        let mut string = String::new();
+
+        loop {
+            match self.next() {
+                Some('\\') => string.push( self.next().ok_or_else( || self.error( "unfinished escape" ) )? ),
+                Some(c) if c == quote => return Ok(string),
+                Some(c) => string.push( c ),
+                None    => return Err(self.error( "unterminated string" ))
+            }
+        }
+    }
+
+    fn selector_list( &mut self ) -> Result<SelectorList, String> {
+        let mut list = vec![];
+
+        loop {
+            self.skip_whitespace();
+            list.push( self.complex()? );
+            self.skip_whitespace();
+
+            if !self.eat( ',' ) { break }
+        }
+
+        Ok(SelectorList( list ))
+    }
+
+    fn complex( &mut self ) -> Result<Complex, String> {
+        let mut compounds = vec![(Combinator::Descendant, self.compound()?)];
+
+        loop {
+            let whitespace = self.skip_whitespace();
+
+            let combinator = match self.peek() {
+                Some('>') => Combinator::Child,
+                Some('+') => Combinator::NextSibling,
+                Some('~') => Combinator::SubsequentSibling,
+                Some(',') | Some(')') | None => break,
+                Some(_) if whitespace => Combinator::Descendant,
+                Some(_) => return Err(self.error( "unexpected character" ))
+            };
+
+            if combinator != Combinator::Descendant {
+                self.position += 1;
+                self.skip_whitespace();
+            }
+
+            compounds.push( (combinator, self.compound()?) );
+        }
+
+        Ok(Complex( compounds ))
+    }
+
+    fn compound( &mut self ) -> Result<Compound, String> {
+        let mut compound = Compound::default();
+        let start        = self.position;
+
+        let universal = self.eat( '*' );
+        if !universal && self.peek().map_or( false, |c| Parser::is_identifier_char( c ) || c == '\\' ) {
+            compound.name = Some(self.identifier()?.to_lowercase());
+        }
+
+        loop {
+            let condition = match self.peek() {
+                Some('#') => {
+                    self.position += 1;
+                    Condition::Id( self.identifier()? )
+                },
+                Some('.') => {
+                    self.position += 1;
+                    Condition::Class( self.identifier()? )
+                },
+                Some('[') => {
+                    self.position += 1;
+                    self.attribute()?
+                },
+                Some(':') => {
+                    self.position += 1;
+                    self.pseudo_class()?
+                },
+                _ => break
+            };
+
+            compound.conditions.push( condition );
+        }
+
+        if self.position == start { return Err(self.error( "expected a selector" )) }
+        Ok(compound)
+    }
+
+    fn attribute( &mut self ) -> Result<Condition, String> {
+        self.skip_whitespace();
+        let name = self.identifier()?.to_lowercase();
+        self.skip_whitespace();
+
+        let operator = match self.next() {
+            Some(']') => return Ok(Condition::Attribute {
+                name:             name,
+                operator:         Operator::Exists,
+                value:            String::new(),
+                case_insensitive: false
+            }),
+            Some('=') => Operator::Equals,
+            Some(c @ '~') | Some(c @ '|') | Some(c @ '^') | Some(c @ '$') | Some(c @ '*') => {
+                self.expect( '=' )?;
+
+                match c {
+                    '~' => Operator::Includes,
+                    '|' => Operator::DashMatch,
+                    '^' => Operator::Prefix,
+                    '$' => Operator::Suffix,
+                    _   => Operator::Substring
+                }
+            },
+            _ => return Err(self.error( "expected an attribute operator" ))
+        };
+
+        self.skip_whitespace();
+        let value = match self.peek() {
+            Some(quote @ '"') | Some(quote @ '\'') => {
+                self.position += 1;
+                self.string( quote )?
+            },
+            _ => self.identifier()?
+        };
+        self.skip_whitespace();
+
+        let case_insensitive = self.eat( 'i' ) || self.eat( 'I' );
+        self.skip_whitespace();
+        self.expect( ']' )?;
+
+        Ok(Condition::Attribute {
+            name:             name,
+            operator:         operator,
+            value:            value,
+            case_insensitive: case_insensitive
+        })
+    }
+
+
+    fn pseudo_class( &mut self ) -> Result<Condition, String> {
+        let name = self.identifier()?.to_lowercase();
+
+        match name.as_str() {
+            "first-child" => return Ok(Condition::NthChild { a: 0, b: 1, last: false }),
+            "last-child"  => return Ok(Condition::NthChild { a: 0, b: 1, last: true }),
+            _             => {}
+        }
+
+        self.expect( '(' )?;
+
+        let condition = match name.as_str() {
+            "not"            => Condition::Not( self.selector_list()? ),
+            "nth-child"      => self.nth( false )?,
+            "nth-last-child" => self.nth( true )?,
+            _                => return Err(format!( "unsupported pseudo-class ':{}'", name ))
+        };
+
+        self.skip_whitespace();
+        self.expect( ')' )?;
+
+        Ok(condition)
+    }
+
+    /// Parses `odd`, `even`, `b` and `an+b`.
+    fn nth( &mut self, last: bool ) -> Result<Condition, String> {
+        let mut expression = String::new();
+        while let Some(c) = self.peek() {
+            if c == ')' { break }
+            if !c.is_whitespace() { expression.push( c.to_ascii_lowercase() ) }
+            self.position += 1;
+        }
+
+        let invalid = || format!( "invalid :nth-child() argument '{}'", expression );
+
+        let (a, b) = match expression.as_str() {
+            "odd"  => (2, 1),
+            "even" => (2, 0),
+            _      => match expression.find( 'n' ) {
+                Some(i) => {
+                    let a = match &expression[..i] {
+                        ""  | "+" => 1,
+                        "-"       => -1,
+                        a         => a.parse().map_err( |_| invalid() )?
+                    };
+
+                    let b = match &expression[i + 1..] {
+                        "" => 0,
+                        b  => b.parse().map_err( |_| invalid() )?
+                    };
+
+                    (a, b)
+                },
+                None => (0, expression.parse().map_err( |_| invalid() )?)
+            }
+        };
+
+        Ok(Condition::NthChild { a: a, b: b, last: last })
+    }
+}
+
+fn is_element( handle: &Handle ) -> bool {
+    if let Enum::Element { .. } = handle.borrow().node { true } else { false }
+}
+
+/// Parent element of `handle`, `None` once `scope` is reached.
+fn parent( handle: &Handle, scope: &Handle ) -> Option<Handle> {
+    let parent = Handle( handle.borrow().parent.as_ref()?.upgrade()? );
+
+    if Rc::ptr_eq( &parent, scope ) || !is_element( &parent ) { return None }
+    Some(parent)
+}
+
+/// Element siblings of `handle`, itself included, and its position amongst them.
+fn siblings( handle: &Handle ) -> (Vec<Handle>, usize) {
+    let parent = match handle.borrow().parent.as_ref().and_then( |p| p.upgrade() ) {
+        Some(parent) => parent,
+        None         => return (vec![handle.clone()], 0)
+    };
+
+    let siblings: Vec<Handle> = parent.borrow().children.iter().
+        filter( |h| is_element( h ) ).
+        cloned().
+        collect();
+
+    let position = siblings.iter().position( |h| Rc::ptr_eq( h, handle ) ).unwrap_or( 0 );
+    (siblings, position)
+}
+
+/// Whether the 1-based `index` is `an+b` for some `n >= 0`.
+fn is_nth( a: i64, b: i64, index: i64 ) -> bool {
+    if a == 0 { return index == b }
+
+    let difference = index - b;
+    difference % a == 0 && difference / a >= 0
+}
+
+impl Condition {
+
+    fn matches( &self, handle: &Handle, attributes: &[Attribute], scope: &Handle ) -> bool {
+        match *self {
+            Condition::Id( ref id ) => attribute( attributes, ID ) == Some(id.as_str()),
+
+            Condition::Class( ref class ) => attribute( attributes, CLASS ).map_or( false, |classes|
+                classes.split_whitespace().any( |c| c == class )
+            ),
+
+            Condition::Attribute { ref name, operator, ref value, case_insensitive } => {
+                let actual = match attribute( attributes, name ) {
+                    Some(actual) => actual,
+                    None         => return false
+                };
+
+                let (actual, value) = if case_insensitive {
+                    (actual.to_lowercase(), value.to_lowercase())
+                } else {
+                    (actual.to_string(), value.clone())
+                };
+
+                match operator {
+                    Operator::Exists    => true,
+                    Operator::Equals    => actual == value,
+                    Operator::Includes  => actual.split_whitespace().any( |v| v == value ),
+                    Operator::DashMatch => actual == value || actual.starts_with( &format!( "{}-", value ) ),
+                    Operator::Prefix    => !value.is_empty() && actual.starts_with( &value ),
+                    Operator::Suffix    => !value.is_empty() && actual.ends_with( &value ),
+                    Operator::Substring => !value.is_empty() && actual.contains( &value )
+                }
+            },
+
+            Condition::Not( ref selectors ) => !selectors.matches( handle, scope ),
+
+            Condition::NthChild { a, b, last } => {
+                let (siblings, position) = siblings( handle );
+                let index = if last { siblings.len() - position } else { position + 1 };
+
+                is_nth( a, b, index as i64 )
+            }
+        }
+    }
+}
+
+fn attribute<'a>( attributes: &'a [Attribute], name: &str ) -> Option<&'a str> {
+    attributes.iter().
+        find( |a| (&*a.name.local).eq_ignore_ascii_case( name ) ).
+        map( |a| &*a.value )
+}
+
+impl Compound {
+
+    fn matches( &self, handle: &Handle, scope: &Handle ) -> bool {
+        let node = handle.borrow();
+
+        let (name, attributes) = match node.node {
+            Enum::Element { ref name, ref attributes, .. } => (name, attributes),
+            _                                             => return false
+        };
+
+        if let Some(ref expected) = self.name {
+            if !(&**name).eq_ignore_ascii_case( expected ) { return false }
+        }
+
+        self.conditions.iter().all( |c| c.matches( handle, attributes, scope ) )
+    }
+}
+
+impl Complex {
+
+    fn matches( &self, handle: &Handle, scope: &Handle ) -> bool {
+        self.matches_at( self.0.len() - 1, handle, scope )
+    }
+
+    /// Matches right to left, starting from the compound at `i`.
+    fn matches_at( &self, i: usize, handle: &Handle, scope: &Handle ) -> bool {
+        let (combinator, ref compound) = self.0[i];
+
+        if !compound.matches( handle, scope ) { return false }
+        if i == 0 { return true }
+
+        match combinator {
+            Combinator::Child => parent( handle, scope ).map_or( false, |p|
+                self.matches_at( i - 1, &p, scope )
+            ),
+
+            Combinator::Descendant => {
+                let mut ancestor = parent( handle, scope );
+
+                while let Some(a) = ancestor {
+                    if self.matches_at( i - 1, &a, scope ) { return true }
+                    ancestor = parent( &a, scope );
+                }
+
+                false
+            },
+
+            Combinator::NextSibling => {
+                let (siblings, position) = siblings( handle );
+
+                position > 0 && self.matches_at( i - 1, &siblings[position - 1], scope )
+            },
+
+            Combinator::SubsequentSibling => {
+                let (siblings, position) = siblings( handle );
+
+                siblings[..position].iter().any( |s| self.matches_at( i - 1, s, scope ) )
+            }
+        }
+    }
+}
+
+impl SelectorList {
+
+    pub fn parse( selector: &str ) -> Result<Self, String> {
+        let mut parser = Parser { chars: selector.chars().collect(), position: 0 };
+        let selectors  = parser.selector_list()?;
+
+        if parser.peek().is_some() { return Err(parser.error( "unexpected character" )) }
+        Ok(selectors)
+    }
+
+    /// Whether the `handle` element matches, combinators don't look above `scope`.
+    pub fn matches( &self, handle: &Handle, scope: &Handle ) -> bool {
+        self.0.iter().any( |complex| complex.matches( handle, scope ) )
+    }
+}
@@ -0,0 +1,460 @@
+//! Spec-compliant tree construction, html5ever's tree builder on top of `node::Handle` trees.
+//!
+//! Unlike the SAX parser, end tags only close what the HTML spec says they close, so implied
+//! ends, misnested formatting elements and foster parenting come out the way a browser has them.
+//! Template contents are kept as children of their `template`.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::default::Default;
+use std::mem;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use tendril::StrTendril;
+
+use html5ever::{LocalName, QualName, ExpandedName, Attribute};
+use html5ever::tokenizer::{BufferQueue, Tokenizer, TokenizerOpts, TokenizerResult, Token, TokenSink, TokenSinkResult};
+use html5ever::tokenizer::{TagToken, StartTag, CommentToken, CharacterTokens};
+use html5ever::tree_builder::{TreeBuilder, TreeBuilderOpts, TreeSink, ElementFlags, NodeOrText, AppendNode, AppendText, QuirksMode};
+
+use parser::sax::{node, filter, handler};
+use parser::sax::node::{Enum, Handle};
+use parser::sax::position::{Locator, Lines, Position, Range};
+
+/// Markup of the token being processed.
+enum Current {
+    Nothing,
+    StartTag( LocalName, (usize, usize) ),
+    Comment( (usize, usize) ),
+    Characters( (usize, usize) )
+}
+
+/// Only offsets are known while building, lines and columns are filled in at the end.
+fn at( offset: usize ) -> Position {
+    Position { offset: offset, line: 0, column: 0 }
+}
+
+fn span( (start, end): (usize, usize) ) -> Range {
+    Range {
+        start: at( start ),
+        end:   at( end )
+    }
+}
+
+fn key( handle: &Handle ) -> usize {
+    &*handle.0 as *const _ as usize
+}
+
+fn parent( handle: &Handle ) -> Option<Handle> {
+    handle.borrow().parent.as_ref().and_then( |parent| parent.upgrade() ).map( Handle )
+}
+
+fn is_text( handle: &Handle ) -> bool {
+    if let Enum::Text( .. ) = handle.borrow().node { true } else { false }
+}
+
+struct Sink {
+    document: Handle,
+
+    /// The tree builder borrows element names, which live behind `RefCell`s in the nodes.
+    names:    HashMap<usize, QualName>,
+
+    current:  Current,
+
+    /// Elements created from start tags and the end tags that came along, in source order.
+    located:  Vec<Handle>,
+    end_tags: Vec<(LocalName, (usize, usize))>
+}
+
+impl Sink {
+    fn new() -> Self {
+        Sink {
+            document: node::Node::new_handle( Enum::Document, None, None ),
+            names:    HashMap::new(),
+            current:  Current::Nothing,
+            located:  vec![],
+            end_tags: vec![]
+        }
+    }
+
+    fn new_text( &self, parent: &Handle, text: StrTendril ) -> Handle {
+        let position = match self.current {
+            Current::Characters( located ) => Some(span( located )),
+            _                              => None
+        };
+
+        node::Node::new_handle(
+            Enum::Text( text.to_string() ),
+            Some(Rc::downgrade( parent )),
+            position
+        )
+    }
+
+    /// Appends to the text of `handle`, whose position only survives if the new text is located
+    /// too.
+    fn merge_text( &self, handle: &Handle, text: &str ) {
+        let mut borrowed = handle.borrow_mut();
+
+        borrowed.position = match (borrowed.position, &self.current) {
+            (Some(range), &Current::Characters( (_, end) )) => Some(Range { start: range.start, end: at( end ) }),
+            _ => None
+        };
+
+        if let Enum::Text( ref mut existing ) = borrowed.node {
+            existing.push_str( text );
+        }
+    }
+
+    fn insert( &mut self, parent: &Handle, index: usize, child: NodeOrText<Handle> ) {
+        match child {
+            AppendNode( handle ) => {
+                self.remove_from_parent( &handle );
+
+                handle.borrow_mut().parent = Some(Rc::downgrade( parent ));
+                parent.borrow_mut().children.insert( index, handle );
+            },
+
+            AppendText( text ) => {
+                let previous = if index > 0 {
+                    Some(parent.borrow().children[index - 1].clone())
+                } else {
+                    None
+                };
+
+                match previous {
+                    Some(ref previous) if is_text( previous ) => self.merge_text( previous, &text ),
+                    _ => {
+                        let handle = self.new_text( parent, text );
+                        parent.borrow_mut().children.insert( index, handle );
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl TreeSink for Sink {
+    type Handle = Handle;
+    type Output = Handle;
+
+    fn finish( self ) -> Handle {
+        self.document
+    }
+
+    fn parse_error( &mut self, _: Cow<'static, str> ) {}
+
+    fn get_document( &mut self ) -> Handle {
+        self.document.clone()
+    }
+
+    fn elem_name<'a>( &'a self, target: &'a Handle ) -> ExpandedName<'a> {
+        self.names.get( &key( target ) ).expect( "Not an element." ).expanded()
+    }
+
+    fn create_element( &mut self, name: QualName, attributes: Vec<Attribute>, _: ElementFlags ) -> Handle {
+        let position = match self.current {
+            Current::StartTag( ref tag_name, located ) if tag_name == &name.local => Some(span( located )),
+            _ => None
+        };
+
+        // A start tag creates one element at most, implied ones come first.
+        if position.is_some() { self.current = Current::Nothing }
+
+        let handle = node::Node::new_handle(
+            Enum::Element {
+                self_closing: handler::SELF_CLOSE.contains( &name.local ),
+                name:         name.local.clone(),
+                attributes:   attributes
+            },
+            None,
+            position
+        );
+
+        if handle.borrow().position.is_some() {
+            self.located.push( handle.clone() );
+        }
+
+        self.names.insert( key( &handle ), name );
+        handle
+    }
+
+    fn create_comment( &mut self, text: StrTendril ) -> Handle {
+        let position = match self.current {
+            Current::Comment( located ) => Some(span( located )),
+            _                           => None
+        };
+
+        node::Node::new_handle( Enum::Comment( text.to_string() ), None, position )
+    }
+
+    fn create_pi( &mut self, _: StrTendril, data: StrTendril ) -> Handle {
+        self.create_comment( data )
+    }
+
+    fn append( &mut self, parent: &Handle, child: NodeOrText<Handle> ) {
+        let index = parent.borrow().children.len();
+        self.insert( parent, index, child )
+    }
+
+    fn append_based_on_parent_node( &mut self, element: &Handle, prev_element: &Handle, child: NodeOrText<Handle> ) {
+        if parent( element ).is_some() {
+            self.append_before_sibling( element, child )
+        } else {
+            self.append( prev_element, child )
+        }
+    }
+
+    fn append_doctype_to_document( &mut self, _: StrTendril, _: StrTendril, _: StrTendril ) {}
+
+    fn get_template_contents( &mut self, target: &Handle ) -> Handle {
+        target.clone()
+    }
+
+    fn same_node( &self, x: &Handle, y: &Handle ) -> bool {
+        Rc::ptr_eq( x, y )
+    }
+
+    fn set_quirks_mode( &mut self, _: QuirksMode ) {}
+
+    fn append_before_sibling( &mut self, sibling: &Handle, child: NodeOrText<Handle> ) {
+        let parent = match parent( sibling ) {
+            Some(parent) => parent,
+            None         => return
+        };
+
+        let index = parent.borrow().children.iter().position( |c| Rc::ptr_eq( c, sibling ) ).unwrap_or( 0 );
+        self.insert( &parent, index, child )
+    }
+
+    fn add_attrs_if_missing( &mut self, target: &Handle, new_attributes: Vec<Attribute> ) {
+        if let Enum::Element { ref mut attributes, .. } = target.borrow_mut().node {
+            for attribute in new_attributes {
+                if attributes.iter().any( |a| a.name == attribute.name ) { continue }
+                attributes.push( attribute );
+            }
+        }
+    }
+
+    fn remove_from_parent( &mut self, target: &Handle ) {
+        if let Some(parent) = parent( target ) {
+            parent.borrow_mut().children.retain( |c| !Rc::ptr_eq( c, target ) );
+        }
+
+        target.borrow_mut().parent = None;
+    }
+
+    fn reparent_children( &mut self, handle: &Handle, new_parent: &Handle ) {
+        let children = mem::replace( &mut handle.borrow_mut().children, vec![] );
+
+        for child in &children {
+            child.borrow_mut().parent = Some(Rc::downgrade( new_parent ));
+        }
+
+        new_parent.borrow_mut().children.extend( children );
+    }
+}
+
+/// Tells the tree builder where each token came from, if positions are tracked.
+struct Tree {
+    builder: TreeBuilder<Handle, Sink>,
+    locator: Option<Locator>
+}
+
+impl TokenSink for Tree {
+    type Handle = Handle;
+
+    fn process_token( &mut self, token: Token, line_number: u64 ) -> TokenSinkResult<Handle> {
+        let current = match (&mut self.locator, &token) {
+            (&mut None, _) => None,
+
+            (&mut Some(ref mut locator), &CharacterTokens( ref text )) =>
+                locator.characters( text ).map( Current::Characters ),
+
+            (&mut Some(ref mut locator), _) => locator.locate( &token ).map( |located| match token {
+                TagToken( ref tag ) if tag.kind == StartTag => Current::StartTag( tag.name.clone(), located ),
+                CommentToken( _ )                           => Current::Comment( located ),
+
+                TagToken( ref tag ) => {
+                    self.builder.sink.end_tags.push( (tag.name.clone(), located) );
+                    Current::Nothing
+                },
+
+                _ => Current::Nothing
+            })
+        };
+
+        self.builder.sink.current = current.unwrap_or( Current::Nothing );
+        let result = self.builder.process_token( token, line_number );
+        self.builder.sink.current = Current::Nothing;
+
+        result
+    }
+
+    fn end( &mut self ) {
+        self.builder.end()
+    }
+
+    fn adjusted_current_node_present_but_not_in_html_namespace( &self ) -> bool {
+        self.builder.adjusted_current_node_present_but_not_in_html_namespace()
+    }
+}
+
+/// Pairs end tags with the elements they close.
+///
+/// The tree builder doesn't say which element an end tag closed, and often pops elements
+/// without telling, so they're paired up in source order instead; that only disagrees with the
+/// tree for misnested markup.
+fn match_end_tags( located: &[Handle], end_tags: &[(LocalName, (usize, usize))] ) {
+    let mut open: Vec<(&Handle, LocalName)> = vec![];
+    let mut located = located.iter().peekable();
+
+    for &(ref name, (start, end)) in end_tags {
+        while let Some(handle) = located.peek().cloned() {
+            let opened = {
+                let borrowed = handle.borrow();
+                match (&borrowed.node, borrowed.position) {
+                    (&Enum::Element { ref name, .. }, Some(range)) if range.start.offset < start => Some(name.clone()),
+                    _ => None
+                }
+            };
+
+            match opened {
+                Some(opened) => open.push( (handle, opened) ),
+                None         => break
+            }
+
+            located.next();
+        }
+
+        if let Some(i) = open.iter().rposition( |&(_, ref opened)| opened == name ) {
+            open[i].0.borrow_mut().end_tag = Some(span( (start, end) ));
+            open.truncate( i );
+        }
+    }
+}
+
+/// Trims text like the SAX parser does, dropping what's left empty, and fills in lines and
+/// columns.
+/// `lines` is only needed if positions were tracked.
+fn normalize( handle: &Handle, lines: Option<&Lines>, source: &str ) {
+    let children = mem::replace( &mut handle.borrow_mut().children, vec![] );
+    let mut kept = vec![];
+
+    for child in children {
+        {
+            let mut borrowed = child.borrow_mut();
+            let node         = &mut *borrowed;
+
+            if let Enum::Text( ref mut text ) = node.node {
+                let trimmed = text.trim().to_string();
+                if trimmed.is_empty() { continue }
+                *text = trimmed;
+
+                node.position = node.position.map( |range| {
+                    let raw = &source[range.start.offset..range.end.offset];
+                    span( (
+                        range.start.offset + raw.len() - raw.trim_start().len(),
+                        range.end.offset - ( raw.len() - raw.trim_end().len() )
+                    ))
+                });
+            }
+
+            if let Some(lines) = lines {
+                let resolve = |range: Range| Range {
+                    start: lines.position( range.start.offset ),
+                    end:   lines.position( range.end.offset )
+                };
+
+                node.position = node.position.map( &resolve );
+                node.end_tag  = node.end_tag.map( &resolve );
+            }
+        }
+
+        normalize( &child, lines, source );
+        kept.push( child );
+    }
+
+    handle.borrow_mut().children = kept;
+}
+
+/// Keeps what `profile` allows, the children of elements it doesn't go to their closest
+/// ancestor that's kept, same as with the SAX handler.
+fn prune( handle: &Handle, profile: &filter::Profile ) {
+    let name = match handle.borrow().node {
+        Enum::Element { ref name, .. } => Some(name.clone()),
+        _                              => None
+    };
+
+    let children = allowed_children( handle, name.as_ref(), profile );
+
+    for child in &children {
+        child.borrow_mut().parent = Some(Rc::downgrade( handle ));
+    }
+
+    handle.borrow_mut().children = children;
+}
+
+fn allowed_children( handle: &Handle, parent: Option<&LocalName>, profile: &filter::Profile ) -> Vec<Handle> {
+    let children = mem::replace( &mut handle.borrow_mut().children, vec![] );
+    let mut kept = vec![];
+
+    for child in children {
+        let allowed = match child.borrow().node {
+            Enum::Element { ref name, ref attributes, .. } => profile.allow_element( name, attributes ),
+            Enum::Text( .. ) => parent.map_or( false, |parent| profile.allow_text( parent ) ),
+            _                => true
+        };
+
+        if allowed {
+            prune( &child, profile );
+            kept.push( child );
+        } else if !is_text( &child ) {
+            kept.extend( allowed_children( &child, parent, profile ) );
+        }
+    }
+
+    kept
+}
+
+/// Node positions are only tracked if `positions` is set, it costs a copy of the source and
+/// locating every token in it.
+pub fn parse( html: &str, filter: Option<Arc<filter::Profile>>, positions: bool ) -> Handle {
+    let opts = TreeBuilderOpts {
+        drop_doctype: true,
+        .. Default::default()
+    };
+
+    let locator = if positions {
+        let mut locator = Locator::new();
+        locator.push( html );
+        Some(locator)
+    } else {
+        None
+    };
+
+    let tree = Tree {
+        builder: TreeBuilder::new( Sink::new(), opts ),
+        locator: locator
+    };
+
+    let mut tokenizer = Tokenizer::new( tree, TokenizerOpts { .. Default::default() } );
+
+    let mut input = BufferQueue::new();
+    input.push_back( StrTendril::from_slice( html ) );
+
+    // Scripts pause the tokenizer, there's nothing to run so carry on.
+    while let TokenizerResult::Script( _ ) = tokenizer.feed( &mut input ) {}
+    tokenizer.end();
+
+    let sink     = &tokenizer.sink.builder.sink;
+    let document = sink.document.clone();
+
+    match_end_tags( &sink.located, &sink.end_tags );
+
+    let lines = if positions { Some(Lines::new( html )) } else { None };
+    normalize( &document, lines.as_ref(), html );
+    if let Some(profile) = filter { prune( &document, &profile ) }
+
+    document
+}
@@ -0,0 +1,926 @@
+//! XPath 1.0 subset for `node::Handle` trees.
+//!
+//! Supports location paths with the `child`, `descendant`, `descendant-or-self`, `self`,
+//! `parent`, `ancestor`, `following-sibling`, `preceding-sibling` and `attribute` axes, their
+//! abbreviations (`//`, `.`, `..`, `@`), the `*`, `text()`, `comment()` and `node()` tests, unions,
+//! and predicates with comparisons, `and`, `or` and the `last()`, `position()`, `count()`,
+//! `contains()`, `starts-with()`, `not()`, `normalize-space()`, `string()` and `name()` functions.
+
+use std::rc::Rc;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use parser::sax::node::{Enum, Handle};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Slash,
+    DoubleSlash,
+    OpenBracket,
+    CloseBracket,
+    OpenParen,
+    CloseParen,
+    At,
+    Comma,
+    Pipe,
+    Dot,
+    DoubleDot,
+    DoubleColon,
+    Star,
+    Operator( Operator ),
+    Name( String ),
+    Literal( String ),
+    Number( f64 )
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual
+}
+
+fn tokenize( expression: &str ) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = expression.chars().collect();
+    let mut tokens       = vec![];
+    let mut i            = 0;
+
+    while i < chars.len() {
+        let c    = chars[i];
+        let next = chars.get( i + 1 ).cloned();
+
+        if c.is_whitespace() {
+            i += 1;
+            continue
+        }
+
+        let (token, length) = match c {
+            '/' if next == Some('/') => (Token::DoubleSlash, 2),
+            '/'                      => (Token::Slash, 1),
+            '[' => (Token::OpenBracket, 1),
+            ']' => (Token::CloseBracket, 1),
+            '(' => (Token::OpenParen, 1),
+            ')' => (Token::CloseParen, 1),
+            '@' => (Token::At, 1),
+            ',' => (Token::Comma, 1),
+            '|' => (Token::Pipe, 1),
+            '*' => (Token::Star, 1),
+            ':' if next == Some(':') => (Token::DoubleColon, 2),
+            '.' if next == Some('.') => (Token::DoubleDot, 2),
+            '.' if !next.map_or( false, |n| n.is_ascii_digit() ) => (Token::Dot, 1),
+            '=' => (Token::Operator( Operator::Equal ), 1),
+            '!' if next == Some('=') => (Token::Operator( Operator::NotEqual ), 2),
+            '<' if next == Some('=') => (Token::Operator( Operator::LessOrEqual ), 2),
+            '<'                      => (Token::Operator( Operator::Less ), 1),
+            '>' if next == Some('=') => (Token::Operator( Operator::GreaterOrEqual ), 2),
+            '>'                      => (Token::Operator( Operator::Greater ), 1),
+
+            '"' | '\'' => {
+                let end = chars[i + 1..].iter().position( |&q| q == c ).
+                    ok_or_else( || format!( "unterminated string at position {}", i ) )?;
+
+                let literal = chars[i + 1..i + 1 + end].iter().collect();
+                (Token::Literal( literal ), end + 2)
+            },
+
+            _ if c.is_ascii_digit() || c == '.' => {
+                let length = chars[i..].iter().
+                    take_while( |&&d| d.is_ascii_digit() || d == '.' ).
+                    count();
+
+                let number: String = chars[i..i + length].iter().collect();
+                let number = number.parse().
+                    map_err( |_| format!( "invalid number '{}' at position {}", number, i ) )?;
+
+                (Token::Number( number ), length)
+            },
+
+            _ if c.is_alphabetic() || c == '_' => {
+                let length = chars[i..].iter().
+                    take_while( |&&d| d.is_alphanumeric() || d == '_' || d == '-' || d == '.' ).
+                    count();
+
+                (Token::Name( chars[i..i + length].iter().collect() ), length)
+            },
+
+            _ => return Err(format!( "unexpected '{}' at position {}", c, i ))
+        };
+
+        tokens.push( token );
+        i += length;
+    }
+
+    Ok(tokens)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Axis {
+    Child,
+    Descendant,
+    DescendantOrSelf,
+    SelfNode,
+    Parent,
+    Ancestor,
+    FollowingSibling,
+    PrecedingSibling,
+    Attribute
+}
+
+impl Axis {
+    fn from_name( name: &str ) -> Option<Axis> {
+        Some(match name {
+            "child"              => Axis::Child,
+            "descendant"         => Axis::Descendant,
+            "descendant-or-self" => Axis::DescendantOrSelf,
+            "self"               => Axis::SelfNode,
+            "parent"             => Axis::Parent,
+            "ancestor"           => Axis::Ancestor,
+            "following-sibling"  => Axis::FollowingSibling,
+            "preceding-sibling"  => Axis::PrecedingSibling,
+            "attribute"          => Axis::Attribute,
+            _                    => return None
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum NodeTest {
+    Name( String ),
+    Any,
+    Text,
+    Comment,
+    Node
+}
+
+#[derive(Debug, PartialEq)]
+struct Step {
+    axis:       Axis,
+    test:       NodeTest,
+    predicates: Vec<Expr>
+}
+
+#[derive(Debug, PartialEq)]
+enum Expr {
+    Or( Box<Expr>, Box<Expr> ),
+    And( Box<Expr>, Box<Expr> ),
+    Compare( Operator, Box<Expr>, Box<Expr> ),
+    Union( Box<Expr>, Box<Expr> ),
+
+    /// Predicates on a parenthesized expression, like `(//a)[1]`.
+    Filter( Box<Expr>, Vec<Expr> ),
+    Function( String, Vec<Expr> ),
+    Path { absolute: bool, steps: Vec<Step> },
+    Literal( String ),
+    Number( f64 )
+}
+
+const FUNCTIONS: [&str; 9] = [
+    "last", "position", "count", "contains", "starts-with", "not", "normalize-space", "string", "name"
+];
+
+struct Parser {
+    tokens:   Vec<Token>,
+    position: usize
+}
+
+impl Parser {
+
+    fn peek( &self ) -> Option<&Token> {
+        self.tokens.get( self.position )
+    }
+
+    fn peek_at( &self, offset: usize ) -> Option<&Token> {
+        self.tokens.get( self.position + offset )
+    }
+
+    fn eat( &mut self, token: &Token ) -> bool {
+        if self.peek() != Some(token) { return false }
+        self.position += 1;
+        true
+    }
+
+    fn expect( &mut self, token: &Token ) -> Result<(), String> {
+        if self.eat( token ) { return Ok(()) }
+        Err(self.error( &format!( "expected {:?}", token ) ))
+    }
+
+    fn eat_keyword( &mut self, keyword: &str ) -> bool {
+        if let Some(&Token::Name( ref name )) = self.peek() {
+            if name == keyword {
+                self.position += 1;
+                return true
+            }
+        }
+
+        false
+    }
+
+    fn error( &self, reason: &str ) -> String {
+        match self.peek() {
+            Some(token) => format!( "{} at {:?}, token {}", reason, token, self.position ),
+            None        => format!( "{} at the end", reason )
+        }
+    }
+
+    fn or( &mut self ) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+
+        while self.eat_keyword( "or" ) {
+            expr = Expr::Or( Box::new( expr ), Box::new( self.and()? ) );
+        }
+
+        Ok(expr)
+    }
+
+    fn and( &mut self ) -> Result<Expr, String> {
+        let mut expr = self.comparison()?;
+
+        while self.eat_keyword( "and" ) {
+            expr = Expr::And( Box::new( expr ), Box::new( self.comparison()? ) );
+        }
+
+        Ok(expr)
+    }
+
+    fn comparison( &mut self ) -> Result<Expr, String> {
+        let mut expr = self.union()?;
+
+        while let Some(&Token::Operator( operator )) = self.peek() {
+            self.position += 1;
+            expr = Expr::Compare( operator, Box::new( expr ), Box::new( self.union()? ) );
+        }
+
+        Ok(expr)
+    }
+
+    fn union( &mut self ) -> Result<Expr, String> {
+        let mut expr = self.primary()?;
+
+        while self.eat( &Token::Pipe ) {
+            expr = Expr::Union( Box::new( expr ), Box::new( self.primary()? ) );
+        }
+
+        Ok(expr)
+    }
+
+    fn primary( &mut self ) -> Result<Expr, String> {
+        match self.peek().cloned() {
+            Some(Token::Literal( literal )) => {
+                self.position += 1;
+                Ok(Expr::Literal( literal ))
+            },
+
+            Some(Token::Number( number )) => {
+                self.position += 1;
+                Ok(Expr::Number( number ))
+            },
+
+            Some(Token::OpenParen) => {
+                self.position += 1;
+                let expr = self.or()?;
+                self.expect( &Token::CloseParen )?;
+
+                let predicates = self.predicates()?;
+                if predicates.is_empty() { return Ok(expr) }
+
+                Ok(Expr::Filter( Box::new( expr ), predicates ))
+            },
+
+            Some(Token::Name( ref name )) if self.peek_at( 1 ) == Some(&Token::OpenParen) &&
+                !is_node_type( name ) => self.function(),
+
+            _ => self.path()
+        }
+    }
+
+    fn function( &mut self ) -> Result<Expr, String> {
+        let name = match self.peek().cloned() {
+            Some(Token::Name( name )) => name,
+            _                         => return Err(self.error( "expected a function name" ))
+        };
+
+        if !FUNCTIONS.contains( &name.as_str() ) {
+            return Err(self.error( &format!( "unsupported function '{}()'", name ) ))
+        }
+
+        self.position += 2;
+
+        let mut arguments = vec![];
+        if !self.eat( &Token::CloseParen ) {
+            loop {
+                arguments.push( self.or()? );
+                if !self.eat( &Token::Comma ) { break }
+            }
+
+            self.expect( &Token::CloseParen )?;
+        }
+
+        Ok(Expr::Function( name, arguments ))
+    }
+
+    fn path( &mut self ) -> Result<Expr, String> {
+        let mut steps = vec![];
+
+        let absolute = match self.peek() {
+            Some(&Token::Slash) => {
+                self.position += 1;
+
+                // Just `/`, the root.
+                if !self.is_step_start() {
+                    return Ok(Expr::Path { absolute: true, steps: steps })
+                }
+
+                true
+            },
+
+            Some(&Token::DoubleSlash) => {
+                self.position += 1;
+                steps.push( descendant_or_self() );
+                true
+            },
+
+            _ => false
+        };
+
+        steps.push( self.step()? );
+
+        loop {
+            if self.eat( &Token::DoubleSlash ) {
+                steps.push( descendant_or_self() );
+            } else if !self.eat( &Token::Slash ) {
+                break
+            }
+
+            steps.push( self.step()? );
+        }
+
+        Ok(Expr::Path { absolute: absolute, steps: steps })
+    }
+
+    fn is_step_start( &self ) -> bool {
+        match self.peek() {
+            Some(&Token::Name(..)) | Some(&Token::Star) | Some(&Token::At) |
+                Some(&Token::Dot) | Some(&Token::DoubleDot) => true,
+            _ => false
+        }
+    }
+
+    fn step( &mut self ) -> Result<Step, String> {
+        if self.eat( &Token::Dot ) {
+            return Ok(Step { axis: Axis::SelfNode, test: NodeTest::Node, predicates: self.predicates()? })
+        }
+
+        if self.eat( &Token::DoubleDot ) {
+            return Ok(Step { axis: Axis::Parent, test: NodeTest::Node, predicates: self.predicates()? })
+        }
+
+        let axis = if self.eat( &Token::At ) {
+            Axis::Attribute
+        } else if let (Some(&Token::Name( ref name )), Some(&Token::DoubleColon)) = (self.peek(), self.peek_at( 1 )) {
+            let axis = Axis::from_name( name ).ok_or_else( || self.error( "unsupported axis" ) )?;
+            self.position += 2;
+            axis
+        } else {
+            Axis::Child
+        };
+
+        let test = match self.peek().cloned() {
+            Some(Token::Star) => {
+                self.position += 1;
+                NodeTest::Any
+            },
+
+            Some(Token::Name( ref name )) if is_node_type( name ) && self.peek_at( 1 ) == Some(&Token::OpenParen) => {
+                self.position += 2;
+                self.expect( &Token::CloseParen )?;
+
+                match name.as_str() {
+                    "text"    => NodeTest::Text,
+                    "comment" => NodeTest::Comment,
+                    _         => NodeTest::Node
+                }
+            },
+
+            Some(Token::Name( name )) => {
+                self.position += 1;
+                NodeTest::Name( name.to_lowercase() )
+            },
+
+            _ => return Err(self.error( "expected a node test" ))
+        };
+
+        Ok(Step { axis: axis, test: test, predicates: self.predicates()? })
+    }
+
+    fn predicates( &mut self ) -> Result<Vec<Expr>, String> {
+        let mut predicates = vec![];
+
+        while self.eat( &Token::OpenBracket ) {
+            predicates.push( self.or()? );
+            self.expect( &Token::CloseBracket )?;
+        }
+
+        Ok(predicates)
+    }
+}
+
+fn is_node_type( name: &str ) -> bool {
+    name == "text" || name == "comment" || name == "node"
+}
+
+fn descendant_or_self() -> Step {
+    Step { axis: Axis::DescendantOrSelf, test: NodeTest::Node, predicates: vec![] }
+}
+
+/// A node, or an element attribute by index.
+#[derive(Clone, Debug)]
+pub enum Item {
+    Node( Handle ),
+    Attribute( Handle, usize )
+}
+
+impl Item {
+
+    /// Node pointer and attribute index, equal for items that point to the same thing.
+    fn identity( &self ) -> (*const (), Option<usize>) {
+        match *self {
+            Item::Node( ref handle )         => (Rc::as_ptr( handle ) as *const (), None),
+            Item::Attribute( ref handle, i ) => (Rc::as_ptr( handle ) as *const (), Some(i))
+        }
+    }
+
+    /// XPath string-value.
+    pub fn string_value( &self ) -> String {
+        match *self {
+            Item::Attribute( ref handle, i ) => {
+                if let Enum::Element { ref attributes, .. } = handle.borrow().node {
+                    return attributes[i].value.to_string()
+                }
+
+                String::new()
+            },
+
+            Item::Node( ref handle ) => match handle.borrow().node {
+                Enum::Text( ref text ) | Enum::Comment( ref text ) => text.clone(),
+
+                _ => {
+                    let mut value = String::new();
+                    handle.traverse( |h| {
+                        if let Enum::Text( ref text ) = h.borrow().node {
+                            value.push_str( text );
+                        }
+                    });
+                    value
+                }
+            }
+        }
+    }
+
+    fn name( &self ) -> String {
+        match *self {
+            Item::Node( ref handle ) => match handle.borrow().node {
+                Enum::Element { ref name, .. } => name.to_string(),
+                _                              => String::new()
+            },
+
+            Item::Attribute( ref handle, i ) => match handle.borrow().node {
+                Enum::Element { ref attributes, .. } => attributes[i].name.local.to_string(),
+                _                                    => String::new()
+            }
+        }
+    }
+}
+
+/// Position of every node in a tree, indexed once per `XPath::select` so that node-sets can
+/// be sorted without walking up the tree for each item.
+struct DocumentOrder( HashMap<*const (), usize> );
+
+impl DocumentOrder {
+
+    fn new( root: &Handle ) -> Self {
+        let mut positions = HashMap::new();
+        positions.insert( Rc::as_ptr( root ) as *const (), 0 );
+
+        root.traverse( |handle| {
+            let position = positions.len();
+            positions.insert( Rc::as_ptr( handle ) as *const (), position );
+        });
+
+        DocumentOrder( positions )
+    }
+
+    /// Attributes come right after their element, before its children.
+    fn of( &self, item: &Item ) -> (usize, Option<usize>) {
+        let (node, attribute) = item.identity();
+        (self.0.get( &node ).cloned().unwrap_or( usize::MAX ), attribute)
+    }
+}
+
+fn parent( handle: &Handle ) -> Option<Handle> {
+    handle.borrow().parent.as_ref().and_then( |p| p.upgrade() ).map( Handle )
+}
+
+fn root( handle: &Handle ) -> Handle {
+    let mut root = handle.clone();
+    while let Some(parent) = parent( &root ) { root = parent }
+    root
+}
+
+/// Siblings of `handle` before and after it.
+fn siblings( handle: &Handle ) -> (Vec<Handle>, Vec<Handle>) {
+    let parent = match parent( handle ) {
+        Some(parent) => parent,
+        None         => return (vec![], vec![])
+    };
+
+    let children = parent.borrow().children.clone();
+    let position = children.iter().position( |c| Rc::ptr_eq( c, handle ) ).unwrap_or( 0 );
+
+    (children[..position].to_vec(), children[position + 1..].to_vec())
+}
+
+/// Nodes along `axis`, in proximity order.
+fn axis_items( axis: Axis, item: &Item ) -> Vec<Item> {
+    let handle = match *item {
+        Item::Node( ref handle ) => handle,
+
+        Item::Attribute( ref handle, _ ) => return match axis {
+            Axis::SelfNode | Axis::DescendantOrSelf => vec![item.clone()],
+            Axis::Parent                            => vec![Item::Node( handle.clone() )],
+            Axis::Ancestor                          => {
+                let mut ancestors = vec![Item::Node( handle.clone() )];
+                ancestors.extend( axis_items( Axis::Ancestor, &Item::Node( handle.clone() ) ) );
+                ancestors
+            },
+            _ => vec![]
+        }
+    };
+
+    let mut items = vec![];
+
+    match axis {
+        Axis::Child => {
+            items.extend( handle.borrow().children.iter().map( |h| Item::Node( h.clone() ) ) );
+        },
+
+        Axis::Descendant | Axis::DescendantOrSelf => {
+            if axis == Axis::DescendantOrSelf { items.push( item.clone() ) }
+            handle.traverse( |h| items.push( Item::Node( h.clone() ) ) );
+        },
+
+        Axis::SelfNode => items.push( item.clone() ),
+
+        Axis::Parent => items.extend( parent( handle ).map( Item::Node ) ),
+
+        Axis::Ancestor => {
+            let mut current = parent( handle );
+            while let Some(ancestor) = current {
+                current = parent( &ancestor );
+                items.push( Item::Node( ancestor ) );
+            }
+        },
+
+        Axis::FollowingSibling => {
+            items.extend( siblings( handle ).1.into_iter().map( Item::Node ) );
+        },
+
+        Axis::PrecedingSibling => {
+            items.extend( siblings( handle ).0.into_iter().rev().map( Item::Node ) );
+        },
+
+        Axis::Attribute => {
+            if let Enum::Element { ref attributes, .. } = handle.borrow().node {
+                items.extend( (0..attributes.len()).map( |i| Item::Attribute( handle.clone(), i ) ) );
+            }
+        }
+    }
+
+    items
+}
+
+fn test_matches( test: &NodeTest, axis: Axis, item: &Item ) -> bool {
+    if let Item::Attribute(..) = *item {
+        return match *test {
+            NodeTest::Node          => true,
+            NodeTest::Any           => axis == Axis::Attribute,
+            NodeTest::Name( ref n ) => axis == Axis::Attribute && item.name().eq_ignore_ascii_case( n ),
+            _                       => false
+        }
+    }
+
+    let handle = match *item {
+        Item::Node( ref handle ) => handle,
+        _                        => return false
+    };
+
+    let node = handle.borrow();
+
+    match (test, &node.node) {
+        (&NodeTest::Node, _)                                    => true,
+        (&NodeTest::Text, &Enum::Text(..))                      => true,
+        (&NodeTest::Comment, &Enum::Comment(..))                => true,
+        (&NodeTest::Any, &Enum::Element { .. })                 => axis != Axis::Attribute,
+        (&NodeTest::Name( ref n ), &Enum::Element { ref name, .. }) => {
+            axis != Axis::Attribute && (&**name).eq_ignore_ascii_case( n )
+        },
+        _ => false
+    }
+}
+
+#[derive(Debug)]
+enum Value {
+    Items( Vec<Item> ),
+    String( String ),
+    Number( f64 ),
+    Boolean( bool )
+}
+
+impl Value {
+
+    fn boolean( &self ) -> bool {
+        match *self {
+            Value::Items( ref items ) => !items.is_empty(),
+            Value::String( ref s )    => !s.is_empty(),
+            Value::Number( n )        => n != 0.0 && !n.is_nan(),
+            Value::Boolean( b )       => b
+        }
+    }
+
+    fn number( &self ) -> f64 {
+        match *self {
+            Value::Number( n )  => n,
+            Value::Boolean( b ) => if b { 1.0 } else { 0.0 },
+            _                   => self.string().trim().parse().unwrap_or( ::std::f64::NAN )
+        }
+    }
+
+    fn string( &self ) -> String {
+        match *self {
+            Value::Items( ref items ) => items.first().map( Item::string_value ).unwrap_or_default(),
+            Value::String( ref s )    => s.clone(),
+            Value::Number( n )        => {
+                if n.fract() == 0.0 && n.is_finite() { format!( "{}", n as i64 ) } else { n.to_string() }
+            },
+            Value::Boolean( b ) => b.to_string()
+        }
+    }
+}
+
+fn compare_atoms( operator: Operator, a: &Value, b: &Value ) -> bool {
+    match operator {
+        Operator::Equal | Operator::NotEqual => {
+            let equal = match (a, b) {
+                (&Value::Boolean(..), _) | (_, &Value::Boolean(..)) => a.boolean() == b.boolean(),
+                (&Value::Number(..), _) | (_, &Value::Number(..))   => a.number() == b.number(),
+                _                                                  => a.string() == b.string()
+            };
+
+            equal == (operator == Operator::Equal)
+        },
+
+        _ => {
+            let ordering = a.number().partial_cmp( &b.number() );
+
+            match operator {
+                Operator::Less           => ordering == Some(Ordering::Less),
+                Operator::LessOrEqual    => ordering == Some(Ordering::Less) || ordering == Some(Ordering::Equal),
+                Operator::Greater        => ordering == Some(Ordering::Greater),
+                _                        => ordering == Some(Ordering::Greater) || ordering == Some(Ordering::Equal)
+            }
+        }
+    }
+}
+
+/// Node-sets compare true if any of their nodes does, as per XPath 1.0 §3.4.
+fn compare( operator: Operator, a: &Value, b: &Value ) -> bool {
+    match (a, b) {
+        (&Value::Items( ref items ), &Value::Boolean(..)) |
+            (&Value::Boolean(..), &Value::Items( ref items )) => {
+            let (a, b) = if let Value::Boolean(..) = *a {
+                (a.boolean(), !items.is_empty())
+            } else {
+                (!items.is_empty(), b.boolean())
+            };
+
+            compare_atoms( operator, &Value::Boolean( a ), &Value::Boolean( b ) )
+        },
+
+        (&Value::Items( ref x ), &Value::Items( ref y )) => x.iter().any( |i|
+            y.iter().any( |j|
+                compare_atoms( operator, &Value::String( i.string_value() ), &Value::String( j.string_value() ) )
+            )
+        ),
+
+        (&Value::Items( ref items ), other) => items.iter().any( |i|
+            compare_atoms( operator, &Value::String( i.string_value() ), other )
+        ),
+
+        (other, &Value::Items( ref items )) => items.iter().any( |i|
+            compare_atoms( operator, other, &Value::String( i.string_value() ) )
+        ),
+
+        _ => compare_atoms( operator, a, b )
+    }
+}
+
+/// Sorts `items` in document order and removes duplicates.
+fn normalize( items: Vec<Item>, order: &DocumentOrder ) -> Vec<Item> {
+    let mut seen = HashSet::with_capacity( items.len() );
+
+    let mut items: Vec<Item> = items.into_iter().
+        filter( |i| seen.insert( i.identity() ) ).
+        collect();
+
+    items.sort_by_key( |i| order.of( i ) );
+    items
+}
+
+struct Context<'a> {
+    item:     &'a Item,
+    position: usize,
+    size:     usize,
+    order:    &'a DocumentOrder
+}
+
+impl Expr {
+
+    fn evaluate( &self, context: &Context ) -> Result<Value, String> {
+        Ok(match *self {
+            Expr::Literal( ref s ) => Value::String( s.clone() ),
+            Expr::Number( n )      => Value::Number( n ),
+
+            Expr::Or( ref a, ref b ) => Value::Boolean(
+                a.evaluate( context )?.boolean() || b.evaluate( context )?.boolean()
+            ),
+
+            Expr::And( ref a, ref b ) => Value::Boolean(
+                a.evaluate( context )?.boolean() && b.evaluate( context )?.boolean()
+            ),
+
+            Expr::Compare( operator, ref a, ref b ) => Value::Boolean(
+                compare( operator, &a.evaluate( context )?, &b.evaluate( context )? )
+            ),
+
+            Expr::Union( ref a, ref b ) => match (a.evaluate( context )?, b.evaluate( context )?) {
+                (Value::Items( mut a ), Value::Items( b )) => {
+                    a.extend( b );
+                    Value::Items( normalize( a, context.order ) )
+                },
+                _ => return Err("union operands must be node-sets".to_string())
+            },
+
+            Expr::Filter( ref expr, ref predicates ) => match expr.evaluate( context )? {
+                Value::Items( items ) => Value::Items( filter( items, predicates, context.order )? ),
+                _                     => return Err("predicates need a node-set".to_string())
+            },
+
+            Expr::Path { absolute, ref steps } => {
+                let start = if absolute {
+                    let handle = match *context.item {
+                        Item::Node( ref h ) | Item::Attribute( ref h, _ ) => h
+                    };
+
+                    Item::Node( root( handle ) )
+                } else {
+                    context.item.clone()
+                };
+
+                let mut items = vec![start];
+                for step in steps {
+                    items = step.evaluate( &items, context.order )?;
+                }
+
+                Value::Items( items )
+            },
+
+            Expr::Function( ref name, ref arguments ) => self.call( name, arguments, context )?
+        })
+    }
+
+    fn call( &self, name: &str, arguments: &[Expr], context: &Context ) -> Result<Value, String> {
+        let arity = |n: usize| -> Result<(), String> {
+            if arguments.len() != n {
+                return Err(format!( "{}() takes {} argument(s), {} given", name, n, arguments.len() ))
+            }
+            Ok(())
+        };
+
+        // Optional argument, defaults to the context node.
+        let argument_or_context = |arguments: &[Expr]| -> Result<Value, String> {
+            match arguments.first() {
+                Some(argument) => argument.evaluate( context ),
+                None           => Ok(Value::Items( vec![context.item.clone()] ))
+            }
+        };
+
+        Ok(match name {
+            "last"     => { arity( 0 )?; Value::Number( context.size as f64 ) },
+            "position" => { arity( 0 )?; Value::Number( context.position as f64 ) },
+            "not"      => { arity( 1 )?; Value::Boolean( !arguments[0].evaluate( context )?.boolean() ) },
+
+            "count" => {
+                arity( 1 )?;
+                match arguments[0].evaluate( context )? {
+                    Value::Items( items ) => Value::Number( items.len() as f64 ),
+                    _                     => return Err("count() takes a node-set".to_string())
+                }
+            },
+
+            "contains" | "starts-with" => {
+                arity( 2 )?;
+                let haystack = arguments[0].evaluate( context )?.string();
+                let needle   = arguments[1].evaluate( context )?.string();
+
+                Value::Boolean( if name == "contains" {
+                    haystack.contains( &needle )
+                } else {
+                    haystack.starts_with( &needle )
+                })
+            },
+
+            "normalize-space" => {
+                let value = argument_or_context( arguments )?.string();
+                Value::String( value.split_whitespace().collect::<Vec<_>>().join( " " ) )
+            },
+
+            "string" => Value::String( argument_or_context( arguments )?.string() ),
+
+            _ => match argument_or_context( arguments )? {
+                Value::Items( items ) => Value::String( items.first().map( Item::name ).unwrap_or_default() ),
+                _                     => return Err("name() takes a node-set".to_string())
+            }
+        })
+    }
+}
+
+/// Applies `predicates` in turn, numbers select by position.
+fn filter( mut items: Vec<Item>, predicates: &[Expr], order: &DocumentOrder ) -> Result<Vec<Item>, String> {
+    for predicate in predicates {
+        let size     = items.len();
+        let mut kept = vec![];
+
+        for (i, item) in items.into_iter().enumerate() {
+            let context = Context { item: &item, position: i + 1, size: size, order: order };
+
+            let keep = match predicate.evaluate( &context )? {
+                Value::Number( n ) => n == (i + 1) as f64,
+                value              => value.boolean()
+            };
+
+            if keep { kept.push( item ) }
+        }
+
+        items = kept;
+    }
+
+    Ok(items)
+}
+
+impl Step {
+
+    fn evaluate( &self, context: &[Item], order: &DocumentOrder ) -> Result<Vec<Item>, String> {
+        let mut results = vec![];
+
+        for item in context {
+            let candidates: Vec<Item> = axis_items( self.axis, item ).into_iter().
+                filter( |i| test_matches( &self.test, self.axis, i ) ).
+                collect();
+
+            results.extend( filter( candidates, &self.predicates, order )? );
+        }
+
+        Ok(normalize( results, order ))
+    }
+}
+
+/// A parsed XPath expression.
+#[derive(Debug, PartialEq)]
+pub struct XPath( Expr );
+
+impl XPath {
+
+    pub fn parse( expression: &str ) -> Result<Self, String> {
+        let mut parser = Parser { tokens: tokenize( expression )?, position: 0 };
+        let expr       = parser.or()?;
+
+        if parser.peek().is_some() { return Err(parser.error( "unexpected token" )) }
+        Ok(XPath( expr ))
+    }
+
+    /// Evaluates the expression with `handle` as the context node.
+    ///
+    /// Returns an error if the result is not a node-set.
+    pub fn select( &self, handle: &Handle ) -> Result<Vec<Item>, String> {
+        let item    = Item::Node( handle.clone() );
+        let order   = DocumentOrder::new( &root( handle ) );
+        let context = Context { item: &item, position: 1, size: 1, order: &order };
+
+        match self.0.evaluate( &context )? {
+            Value::Items( items ) => Ok(items),
+            _                     => Err("expression does not evaluate to a node-set".to_string())
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! Public Suffix List lookups, used by `Engine::URIExt`.
+//!
+//! The list is embedded at compile time from `data/public_suffix_list.dat`, refresh it from
+//! https://publicsuffix.org/list/public_suffix_list.dat when cutting a release.
+
+use std::collections::HashSet;
+use url::idna;
+
+const LIST: &str = include_str!( "../data/public_suffix_list.dat" );
+
+const COMMENT:   &str = "//";
+const WILDCARD:  &str = "*.";
+const EXCEPTION: char = '!';
+const DOT:       char = '.';
+const DOT_S:     &str = ".";
+
+/// Parsed list rules, both the ICANN and private sections.
+struct Rules {
+    /// Plain rules, like `co.uk`.
+    normal:     HashSet<String>,
+
+    /// Wildcard rules, stored without the leading `*.`, so `*.ck` becomes `ck`.
+    wildcards:  HashSet<String>,
+
+    /// Exception rules, stored without the leading `!`, so `!www.ck` becomes `www.ck`.
+    exceptions: HashSet<String>
+}
+
+lazy_static! {
+    static ref RULES: Rules = {
+        let mut rules = Rules {
+            normal:     HashSet::new(),
+            wildcards:  HashSet::new(),
+            exceptions: HashSet::new()
+        };
+
+        for line in LIST.lines() {
+            // Rules end at the first whitespace.
+            let rule = match line.split_whitespace().next() {
+                Some(rule) => rule,
+                None       => continue
+            };
+
+            if rule.starts_with( COMMENT ) { continue }
+
+            // Hosts are stored in their ASCII form so the rules need to match.
+            let rule = if rule.is_ascii() {
+                rule.to_lowercase()
+            } else {
+                match idna::domain_to_ascii( rule ) {
+                    Ok(ascii) => ascii,
+                    Err(_)    => continue
+                }
+            };
+
+            if rule.starts_with( WILDCARD ) {
+                rules.wildcards.insert( rule[WILDCARD.len()..].to_string() );
+            } else if rule.starts_with( EXCEPTION ) {
+                rules.exceptions.insert( rule[1..].to_string() );
+            } else {
+                rules.normal.insert( rule );
+            }
+        }
+
+        rules
+    };
+}
+
+fn labels( host: &str ) -> Option<Vec<&str>> {
+    let host = host.trim_end_matches( DOT );
+    if host.is_empty() { return None }
+
+    let labels: Vec<&str> = host.split( DOT ).collect();
+    if labels.iter().any( |label| label.is_empty() ) { return None }
+
+    Some(labels)
+}
+
+/// Number of labels in the public suffix of `labels`.
+fn suffix_length( labels: &[&str] ) -> usize {
+    // Candidates are checked longest first, so the first hit is the prevailing rule.
+    for i in 0..labels.len() {
+        let candidate = labels[i..].join( DOT_S );
+
+        if RULES.exceptions.contains( &candidate ) {
+            return labels.len() - i - 1
+        }
+
+        if RULES.normal.contains( &candidate ) {
+            return labels.len() - i
+        }
+
+        if i + 1 < labels.len() && RULES.wildcards.contains( &labels[i + 1..].join( DOT_S ) ) {
+            return labels.len() - i
+        }
+    }
+
+    // The implicit `*` rule.
+    1
+}
+
+/// Public suffix of the (lowercase, ASCII) `host`, like `co.uk` for `a.example.co.uk`.
+pub fn public_suffix( host: &str ) -> Option<String> {
+    let labels = labels( host )?;
+    let length = suffix_length( &labels );
+
+    Some(labels[labels.len() - length..].join( DOT_S ))
+}
+
+/// Registrable domain of the (lowercase, ASCII) `host`, i.e. the public suffix plus one label,
+/// like `example.co.uk` for `a.example.co.uk`.
+///
+/// Returns `None` if `host` is itself a public suffix.
+pub fn registrable_domain( host: &str ) -> Option<String> {
+    let labels = labels( host )?;
+    let length = suffix_length( &labels );
+
+    if labels.len() <= length { return None }
+
+    Some(labels[labels.len() - length - 1..].join( DOT_S ))
+}
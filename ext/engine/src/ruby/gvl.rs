@@ -0,0 +1,45 @@
+//! Helpers for running native code with Ruby's Global VM Lock released.
+
+use std::ffi::c_void;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use rb_sys::rb_thread_call_without_gvl;
+
+struct Call<F, R> {
+    f:      Option<F>,
+    result: Option<std::thread::Result<R>>
+}
+
+unsafe extern "C" fn trampoline<F, R>( data: *mut c_void ) -> *mut c_void where F: FnOnce() -> R {
+    let call = &mut *(data as *mut Call<F, R>);
+
+    if let Some(f) = call.f.take() {
+        // Panics must not unwind through Ruby's stack frames, carry them back instead.
+        call.result = Some(panic::catch_unwind( AssertUnwindSafe( f ) ));
+    }
+
+    ptr::null_mut()
+}
+
+/// Runs `f` without holding the GVL, so that other Ruby threads can run in the meantime.
+///
+/// `f` **must not** touch Ruby objects or call into the Ruby API in any way. There is no
+/// unblocking function either, so `f` always runs to completion, keep it CPU-bound.
+pub fn without_gvl<F, R>( f: F ) -> R where F: FnOnce() -> R + Send, R: Send {
+    let mut call = Call { f: Some(f), result: None };
+
+    unsafe {
+        rb_thread_call_without_gvl(
+            Some(trampoline::<F, R>),
+            &mut call as *mut Call<F, R> as *mut c_void,
+            None,
+            ptr::null_mut()
+        );
+    }
+
+    match call.result.expect( "GVL-free call did not run" ) {
+        Ok(result) => result,
+        Err(e)     => panic::resume_unwind( e )
+    }
+}
@@ -1,6 +1,7 @@
 //! Corresponds to extensions to Ruby's STD lib classes.
 
 pub mod string;
+pub mod gvl;
 
 use magnus::Error;
 
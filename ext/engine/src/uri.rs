@@ -1,14 +1,25 @@
 //! Corresponds to `Engine::URI`.
 
+pub mod scope;
+pub mod rewrite;
+pub mod query;
+pub mod template;
+pub mod dump;
+pub mod data_url;
+
 use std::str::FromStr;
-use std::net::Ipv4Addr;
+use std::net::{Ipv4Addr, Ipv6Addr};
 use std::str::pattern::Pattern;
 
 use utilities;
-use url::{Url, percent_encoding};
-use magnus::{class, function, method, prelude::*, Error, RModule};
+use ruby::gvl;
+use public_suffix;
+use url::{Url, percent_encoding, idna};
+use magnus::{class, function, method, prelude::*, Error, ExceptionClass, RClass, RModule, RString, Value};
 use std::collections::HashMap;
 
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use std::hash::{Hash, Hasher};
 // We'll be hashing lots of words and integers and FnvHasher is best for short data.
 use fnv::FnvHasher;
@@ -23,20 +34,26 @@ const QUERY: char = '?';
 const PLUS:  char = '+';
 const FRAG:  char = '#';
 const SEMICOLON: char = ';';
+const PERCENT:   char = '%';
+const IPV6_OPEN:  char = '[';
+const IPV6_CLOSE: char = ']';
 
 const PTTH:   &str = ":ptth";
 const HTTP:   &str = "http";
 const HTTPS:  &str = "https";
+const WS:     &str = "ws";
+const WSS:    &str = "wss";
 const PROTO:  &str = "://";
 const JS:     &str = "javascript:";
 const DATA:   &str = "data:";
 const DOT_S:  &str = ".";
+const DOUBLE_DOT: &str = "..";
 
 const DOUBLE_SLASH:  &str = "//";
 const ENCODED_SPACE: &str = "%20";
-const HTTP_PORT_S:   &str = "80";
 const SLASH_S:       &str = "/";
 const AMP_S:         &str = "&";
+const ZONE_ID_DELIM: &str = "%25";
 
 const HTTP_PORT:  u16 = 80;
 const HTTPS_PORT: u16 = 443;
@@ -51,6 +68,132 @@ define_encode_set! {
     pub QUERY_ENCODE_SET = [percent_encoding::QUERY_ENCODE_SET] | { '[', ']', '^', AMP, PLUS, SEMICOLON, '%' }
 }
 
+/// Whether `URIExt#hash` and `#persistent_hash` should ignore `SESSION_ID_PARAMETERS`, so
+/// that the same page under different sessions isn't audited twice.
+static HASH_WITHOUT_SESSION_IDS: AtomicBool = AtomicBool::new( true );
+
+/// Path parameters that carry session IDs, like Java's `;jsessionid=`.
+const SESSION_ID_PARAMETERS: [&str; 5] = ["jsessionid", "phpsessid", "aspsessionid", "sessionid", "sid"];
+
+// Placeholders for variable path segments.
+const INT_SHAPE:  &str = "{int}";
+const UUID_SHAPE: &str = "{uuid}";
+const HEX_SHAPE:  &str = "{hex}";
+
+/// Shorter hex strings are too likely to be actual words, like `cafe` or `added`.
+const HEX_SHAPE_MIN_LENGTH: usize = 8;
+const UUID_GROUPS: [usize; 5] = [8, 4, 4, 4, 12];
+const DASH: char = '-';
+
+fn is_uuid( segment: &str ) -> bool {
+    let groups: Vec<&str> = segment.split( DASH ).collect();
+
+    groups.len() == UUID_GROUPS.len() &&
+        groups.iter().zip( UUID_GROUPS.iter() ).all( |(group, &length)|
+            group.len() == length && group.bytes().all( |b| b.is_ascii_hexdigit() )
+        )
+}
+
+/// Placeholder for path segments that look like IDs, `None` for everything else.
+fn segment_shape( segment: &str ) -> Option<&'static str> {
+    if segment.is_empty() { return None }
+
+    if segment.bytes().all( |b| b.is_ascii_digit() ) { return Some(INT_SHAPE) }
+    if is_uuid( segment ) { return Some(UUID_SHAPE) }
+
+    if segment.len() >= HEX_SHAPE_MIN_LENGTH &&
+        segment.bytes().all( |b| b.is_ascii_hexdigit() ) &&
+        segment.bytes().any( |b| b.is_ascii_digit() ) {
+        return Some(HEX_SHAPE)
+    }
+
+    None
+}
+
+/// Port implied by `scheme`, `None` for unsupported schemes.
+fn default_port( scheme: &str ) -> Option<u16> {
+    match scheme {
+        HTTP  | WS  => Some(HTTP_PORT),
+        HTTPS | WSS => Some(HTTPS_PORT),
+        _           => None
+    }
+}
+
+/// `SCNR::Engine::URIExt::Error::InvalidURL`, defined on the Ruby side.
+fn invalid_url_error( message: String ) -> Error {
+    let class = class::object().const_get::<_, RModule>( "SCNR" ).
+        and_then( |ns| ns.const_get::<_, RModule>( "Engine" ) ).
+        and_then( |ns| ns.const_get::<_, RClass>( "URIExt" ) ).
+        and_then( |c| c.const_get::<_, RClass>( "Error" ) ).
+        and_then( |c| c.const_get::<_, ExceptionClass>( "InvalidURL" ) ).
+        unwrap_or_else( |_| magnus::exception::arg_error() );
+
+    Error::new( class, message )
+}
+
+fn is_secure( scheme: &str ) -> bool {
+    scheme == HTTPS || scheme == WSS
+}
+
+/// Drops the last segment of `path`, along with its `/`, as per RFC 3986 §5.2.4.
+fn pop_segment( path: &mut String ) {
+    match path.rfind( SLASH ) {
+        Some(i) => path.truncate( i ),
+        None    => path.clear()
+    }
+}
+
+/// `remove_dot_segments` from RFC 3986 §5.2.4, `path` may carry path parameters.
+fn remove_dot_segments( path: &str ) -> String {
+    let mut input  = path.to_string();
+    let mut output = String::with_capacity( path.len() );
+
+    while !input.is_empty() {
+        if input.starts_with( "../" ) {
+            input.drain( ..3 );
+        } else if input.starts_with( "./" ) {
+            input.drain( ..2 );
+        } else if input.starts_with( "/./" ) {
+            input.replace_range( ..3, SLASH_S );
+        } else if input == "/." {
+            input = SLASH_S.to_string();
+        } else if input.starts_with( "/../" ) {
+            input.replace_range( ..4, SLASH_S );
+            pop_segment( &mut output );
+        } else if input == "/.." {
+            input = SLASH_S.to_string();
+            pop_segment( &mut output );
+        } else if input == DOT_S || input == DOUBLE_DOT {
+            input.clear();
+        } else {
+            let start = if input.starts_with( SLASH ) { 1 } else { 0 };
+            let end   = input[start..].find( SLASH ).map_or( input.len(), |i| i + start );
+
+            output.push_str( &input[..end] );
+            input.drain( ..end );
+        }
+    }
+
+    output
+}
+
+/// Merges a relative `path` with the `base` one, as per RFC 3986 §5.2.3.
+fn merge_paths( base: &str, base_has_authority: bool, path: &str ) -> String {
+    if base_has_authority && base.is_empty() {
+        return format!( "{}{}", SLASH, path )
+    }
+
+    match base.rfind( SLASH ) {
+        Some(i) => format!( "{}{}", &base[..=i], path ),
+        None    => path.to_string()
+    }
+}
+
+fn is_session_id( name: &str ) -> bool {
+    let name = name.to_lowercase();
+    SESSION_ID_PARAMETERS.iter().any( |&id| id == name )
+}
+
 fn hash_obj<T: Hash>(t: &T) -> u64 {
     let mut hasher = FnvHasher::default();
     t.hash( &mut hasher );
@@ -78,9 +221,74 @@ fn split_to_vector<'a, P: Pattern>( string: & str, delimeter: P ) -> Vec<String>
     vector
 }
 
+/// Splits `host_port` into the host and the (possibly empty) port string, taking care of
+/// bracketed IPv6 literals whose addresses contain colons of their own.
+fn split_host_and_port( host_port: &str ) -> (String, Option<String>) {
+    if host_port.starts_with( IPV6_OPEN ) {
+        if let Some(close) = host_port.find( IPV6_CLOSE ) {
+            let host = host_port[..=close].to_string();
+            let rest = &host_port[close + 1..];
+
+            if rest.starts_with( COLON ) {
+                return (host, Some(rest[1..].to_string()))
+            }
+
+            return (host, None)
+        }
+    }
+
+    let mut splits = splitn_to_vector( host_port, COLON, 2 );
+    let host = splits.remove( 0 );
+    (host, splits.pop())
+}
+
+/// Normalizes a bracketed IPv6 literal (`[addr]` or `[addr%25zone]`) to its canonical RFC 5952
+/// form, zone IDs are kept as they are but always use the RFC 6874 `%25` delimiter.
+///
+/// Returns `None` if `host` is not a valid IPv6 literal.
+fn normalize_ipv6_literal( host: &str ) -> Option<String> {
+    if !host.starts_with( IPV6_OPEN ) || !host.ends_with( IPV6_CLOSE ) { return None }
+
+    let inner = &host[1..host.len() - 1];
+    let (address, zone) = match inner.find( PERCENT ) {
+        Some(i) => {
+            let zone = &inner[i..];
+            let zone = if zone.starts_with( ZONE_ID_DELIM ) { &zone[3..] } else { &zone[1..] };
+            if zone.is_empty() { return None }
+
+            (&inner[..i], Some(zone))
+        },
+        None => (inner, None)
+    };
+
+    let address = Ipv6Addr::from_str( address ).ok()?;
+
+    let mut normalized = String::with_capacity( host.len() );
+    normalized.push( IPV6_OPEN );
+    normalized.push_str( &address.to_string() );
+
+    if let Some(zone) = zone {
+        normalized.push_str( ZONE_ID_DELIM );
+        normalized.push_str( zone );
+    }
+
+    normalized.push( IPV6_CLOSE );
+    Some(normalized)
+}
+
+/// Normalizes `host` for storage: IPv6 literals are canonicalized, internationalized domain names
+/// are converted to their ASCII (punycode) form and everything else is lowercased.
+fn normalize_host( host: &str ) -> String {
+    if let Some(literal) = normalize_ipv6_literal( host ) { return literal }
+
+    if !host.is_ascii() {
+        if let Ok(ascii) = idna::domain_to_ascii( host ) { return ascii }
+    }
+
+    host.to_lowercase()
+}
+
 /// URL representation.
-#[derive(Hash)]
-#[derive(PartialEq)]
 #[derive(Clone)]
 struct URI {
     scheme:   Option<String>, // Valid schemes are `http` and `https`.
@@ -88,18 +296,50 @@ struct URI {
     host:     Option<String>,
     port:     Option<u16>,
     path:     Option<String>,
-    query:    Option<String>
+    query:    Option<String>,
+
+    /// Matrix parameters as `(segment index, name, value)`, the index counts the non-empty
+    /// segments of `path`.
+    path_parameters: Vec<(usize, String, String)>,
+
+    /// Whether `.` and `..` path segments are kept, like `MutURI::with_dot_segments` does, when
+    /// the URI is resolved or rewritten. Not part of the URL, so left out of comparisons.
+    keep_dot_segments: bool
+}
+
+impl PartialEq for URI {
+    fn eq( &self, other: &URI ) -> bool {
+        self.scheme == other.scheme && self.userinfo == other.userinfo &&
+            self.host == other.host && self.port == other.port && self.path == other.path &&
+            self.query == other.query && self.path_parameters == other.path_parameters
+    }
 }
 
 impl URI {
     pub fn fast_parse( u: String ) -> URI {
+        URI::parse( u, true )
+    }
+
+    /// Parses and normalizes `u`.
+    ///
+    /// Unless `remove_dot_segments` is `false`, `.` and `..` segments of absolute paths are
+    /// resolved as per RFC 3986 section 5.2.4. Relative paths always keep them, since their
+    /// meaning depends on the reference they'll be resolved against.
+    pub fn parse( u: String, remove_dot_segments: bool ) -> URI {
+        URI::parse_with( u, remove_dot_segments, true )
+    }
+
+    /// Like `parse`, `decode_entities` is for when HTML entities have already been decoded.
+    fn parse_with( u: String, remove_dot_segments: bool, decode_entities: bool ) -> URI {
         let mut result = URI {
-            scheme:   None,
-            userinfo: None,
-            host:     None,
-            port:     None,
-            path:     None,
-            query:    None
+            scheme:            None,
+            userinfo:          None,
+            host:              None,
+            port:              None,
+            path:              None,
+            query:             None,
+            path_parameters:   vec![],
+            keep_dot_segments: !remove_dot_segments
         };
 
         let mut url = u.to_string();
@@ -112,7 +352,9 @@ impl URI {
         if lowercase_url.starts_with( DATA ) { return result }
 
         // Decode HTML entities:
-        url = utilities::html_decode( &url );
+        if decode_entities {
+            url = utilities::html_decode( &url );
+        }
 
         // One to rip apart.
         url = url.clone();
@@ -138,7 +380,7 @@ impl URI {
         if !splits.is_empty() {
 
             let scheme = splits[0].to_lowercase();
-            if scheme == HTTP || scheme == HTTPS {
+            if default_port( &scheme ).is_some() {
                 // Split the url in 2, scheme/rest.
                 let mut splits = splitn_to_vector( &url, PROTO, 2 );
 
@@ -175,16 +417,13 @@ impl URI {
 
                     // Go for the host and port.
                     if !splits.is_empty() {
-                        let splits = splitn_to_vector( &splits.remove(0), COLON, 2 );
+                        let (host, port) = split_host_and_port( &splits.remove(0) );
+                        result.host = Some(normalize_host( &host ));
 
-                        result.host = Some(splits[0].to_lowercase());
-
-                        if splits.len() == 2 {
-                            let port = &splits[1];
-                            if !port.is_empty() && port != HTTP_PORT_S {
-                                if let Ok(port_number) = port.parse::<u16>() {
-                                    result.port = Some( port_number );
-                                }
+                        // Only keep the port if it's not implied by the scheme.
+                        if let Some(port_number) = port.and_then( |p| p.parse::<u16>().ok() ) {
+                            if result.scheme.as_ref().and_then( |s| default_port( s ) ) != Some(port_number) {
+                                result.port = Some( port_number );
                             }
                         }
 
@@ -202,20 +441,47 @@ impl URI {
         if has_path {
             let mut splits = splitn_to_vector( &url, QUERY, 2 );
             let mut path = splits.remove(0);
-            splits = splitn_to_vector( &path, SEMICOLON, 2 );
-            path = splits.remove(0);
 
             if !path.is_empty() {
-                let had_root_slash = path.starts_with( SLASH );
-                let had_end_slash  = path.ends_with( SLASH );
+                let had_root_slash    = path.starts_with( SLASH );
+                let mut had_end_slash = path.ends_with( SLASH );
+
+                let remove_dot_segments = remove_dot_segments && (had_root_slash || has_scheme);
 
                 let mut encoded_path_segments = vec![];
                 for segment in path.split( SLASH ) {
                     if segment.is_empty() { continue }
 
-                    encoded_path_segments.push(
-                        MutURI::encode_path( &MutURI::decode( segment.to_string() ) )
-                    )
+                    let mut parts = segment.split( SEMICOLON );
+                    let decoded   = MutURI::decode( parts.next().unwrap().to_string() );
+
+                    // Parameters without a segment have nothing to attach to.
+                    if decoded.is_empty() { continue }
+
+                    if remove_dot_segments && (decoded == DOT_S || decoded == DOUBLE_DOT) {
+                        if decoded == DOUBLE_DOT && encoded_path_segments.pop().is_some() {
+                            let index = encoded_path_segments.len();
+                            result.path_parameters.retain( |&(i, _, _)| i != index );
+                        }
+
+                        // `/a/b/..` and `/a/.` both point to a directory.
+                        had_end_slash = true;
+                        continue
+                    }
+
+                    for parameter in parts {
+                        if parameter.is_empty() { continue }
+
+                        let mut pair = parameter.splitn( 2, EQUAL );
+                        result.path_parameters.push((
+                            encoded_path_segments.len(),
+                            MutURI::decode( pair.next().unwrap().to_string() ),
+                            MutURI::decode( pair.next().unwrap_or_default().to_string() )
+                        ));
+                    }
+
+                    had_end_slash = path.ends_with( SLASH );
+                    encoded_path_segments.push( MutURI::encode_path( &decoded ) )
                 }
                 path = encoded_path_segments.join( SLASH_S );
 
@@ -261,13 +527,99 @@ impl URI {
 #[derive(PartialEq)]
 #[derive(Clone)]
 #[magnus::wrap(class = "SCNR::Engine::URIExt")]
-struct MutURI(std::cell::RefCell<URI>);
+pub struct MutURI(std::cell::RefCell<URI>);
 
 impl MutURI {
     fn new( url: String ) -> Self {
         Self(std::cell::RefCell::new(URI::fast_parse( url )))
     }
 
+    /// Parses `url` per the WHATWG URL Standard and only then normalizes it like `new` does.
+    ///
+    /// HTML entities are decoded once, before either parser sees `url`.
+    ///
+    /// Returns the reason `url` was rejected, if it was.
+    fn parse_strict( url: &str ) -> Result<URI, String> {
+        let decoded = utilities::html_decode( url );
+        let parsed  = Url::parse( &decoded ).map_err( |e| format!( "Invalid URL '{}': {}", url, e ) )?;
+
+        if default_port( parsed.scheme() ).is_none() {
+            return Err(format!( "Invalid URL '{}': unsupported scheme '{}'", url, parsed.scheme() ))
+        }
+
+        Ok(URI::parse_with( parsed.into_string(), true, false ))
+    }
+
+    /// Like `new` but raises `URIExt::Error::InvalidURL` instead of returning an invalid URI.
+    fn strict( url: String ) -> Result<Self, Error> {
+        MutURI::parse_strict( &url ).
+            map( |uri| Self(std::cell::RefCell::new( uri )) ).
+            map_err( invalid_url_error )
+    }
+
+    /// Versioned binary form of the parsed fields, see `dump`.
+    fn dump( &self ) -> RString {
+        RString::from_slice( &dump::dump( &self.mself() ) )
+    }
+
+    /// `Marshal` hook, `_level` is ignored.
+    fn marshal_dump( &self, _level: Value ) -> RString {
+        self.dump()
+    }
+
+    /// Restores a URI from `dump` data without parsing it again.
+    fn load( data: RString ) -> Result<Self, Error> {
+        // Copy the bytes out before anything else can touch the string.
+        let bytes = unsafe { data.as_slice() }.to_vec();
+
+        dump::load( &bytes ).
+            map( |uri| Self(std::cell::RefCell::new( uri )) ).
+            map_err( |e| Error::new( magnus::exception::arg_error(), format!( "Invalid URIExt data: {}", e ) ) )
+    }
+
+    /// Like `new` but keeps `.` and `..` path segments as they are, for when the raw form
+    /// matters, like path traversal checks.
+    fn with_dot_segments( url: String ) -> Self {
+        Self(std::cell::RefCell::new(URI::parse( url, false )))
+    }
+
+    /// Absolute and normalized forms of `hrefs`, resolved against `reference`, with `nil` in
+    /// place of the ones that can't be resolved.
+    ///
+    /// The whole batch is processed with the GVL released.
+    fn to_absolute_all( hrefs: Vec<String>, reference: &MutURI ) -> Vec<Option<String>> {
+        let reference = reference.mself().clone();
+
+        gvl::without_gvl( move || {
+            let reference = MutURI(std::cell::RefCell::new( reference ));
+            hrefs.into_iter().map( |href| MutURI::absolute_from( href, &reference ) ).collect()
+        })
+    }
+
+    /// Uncached `URICommon.to_absolute`, minus the fallback to `reference` for hrefs that
+    /// don't point anywhere, like `javascript:` ones.
+    fn absolute_from( href: String, reference: &MutURI ) -> Option<String> {
+        if href.is_empty() { return Some(reference.to_s()) }
+
+        // Scheme-less, inherit the reference's scheme rather than default to HTTP.
+        let href = if href.starts_with( DOUBLE_SLASH ) {
+            let mut h = reference.scheme()?;
+            h.push( COLON );
+            h.push_str( &href );
+            h
+        } else {
+            href
+        };
+
+        let url = MutURI::new( href );
+        if url.is_invalid() { return None }
+
+        url.as_absolute( reference );
+        if url.scheme().is_none() || url.host().is_none() { return None }
+
+        Some(url.to_s())
+    }
+
     fn is_invalid( &self ) -> bool {
         if self.scheme().is_some() { return false }
         if self.userinfo().is_some() { return false }
@@ -299,6 +651,38 @@ impl MutURI {
         self.mself().port
     }
 
+    /// `port`, unless it's the default one for the scheme.
+    fn explicit_port( &self ) -> Option<u16> {
+        let mself = self.mself();
+        let port  = mself.port?;
+
+        match mself.scheme {
+            Some(ref scheme) if default_port( scheme ) == Some(port) => None,
+            _                                                        => Some(port)
+        }
+    }
+
+    /// `userinfo@host:port`, with the port kept even if it's the default one, `None` without a
+    /// host.
+    fn authority( &self ) -> Option<String> {
+        let mself         = self.mself();
+        let mut authority = String::new();
+
+        if let Some(ref userinfo) = mself.userinfo {
+            authority.push_str( userinfo );
+            authority.push( AT );
+        }
+
+        authority.push_str( mself.host.as_ref()? );
+
+        if let Some(port) = mself.port {
+            authority.push( COLON );
+            authority.push_str( &port.to_string() );
+        }
+
+        Some(authority)
+    }
+
     fn query_parameters( &self ) -> HashMap<String, String> {
         if let Some(ref query) = self.query() {
             let mut params = HashMap::new();
@@ -326,6 +710,51 @@ impl MutURI {
         }
     }
 
+    /// Query parameters in order of appearance, duplicates included.
+    fn query_pairs( &self ) -> Vec<(String, String)> {
+        if let Some(ref query) = self.query() {
+            return query::pairs( query )
+        }
+
+        vec![]
+    }
+
+    /// Query parameters structured based on PHP/Rails-style nested names (`a[b][]=1`).
+    fn nested_query_parameters( &self ) -> Result<Value, Error> {
+        query::nested_value( &self.query_pairs() )
+    }
+
+    /// Sets the value of the first `name` parameter and removes its duplicates, the rest of
+    /// the query is left untouched.
+    fn set_param( &self, name: String, value: String ) -> Option<String> {
+        let query = query::set( &self.query().unwrap_or_default(), &name, &value );
+        self.set_query( Some(query) )
+    }
+
+    fn append_param( &self, name: String, value: String ) -> Option<String> {
+        let query = query::append( &self.query().unwrap_or_default(), &name, &value );
+        self.set_query( Some(query) )
+    }
+
+    fn delete_param( &self, name: String ) -> Option<String> {
+        let query = query::delete( &self.query().unwrap_or_default(), &name );
+        self.set_query( Some(query) )
+    }
+
+    /// Replaces the value of the parameter at `index`, as ordered by `query_pairs`.
+    ///
+    /// Returns `false` if there is no such parameter.
+    fn replace_param_value_at( &self, index: usize, value: String ) -> bool {
+        let query = self.query().unwrap_or_default();
+
+        if let Some(query) = query::replace_value_at( &query, index, &value ) {
+            self.set_query( Some(query) );
+            return true
+        }
+
+        false
+    }
+
     fn without_query( &self ) -> String {
         let mut string = String::new();
 
@@ -342,20 +771,13 @@ impl MutURI {
         if let Some(ref host) = self.host() {
             string.push_str( host );
 
-            if let Some(port) = self.port() {
-                if let Some(ref scheme) = self.scheme() {
-                    if (scheme == HTTP && port != HTTP_PORT ) || (scheme == HTTPS && port != HTTPS_PORT ) {
-                        string.push( COLON );
-                        string.push_str( &port.to_string() );
-                    }
-                } else {
-                    string.push( COLON );
-                    string.push_str( &port.to_string() );
-                }
+            if let Some(port) = self.explicit_port() {
+                string.push( COLON );
+                string.push_str( &port.to_string() );
             }
         }
 
-        if let Some(ref path) = self.path() {
+        if let Some(ref path) = self.path_with_parameters() {
             string.push_str( path );
         }
 
@@ -378,16 +800,9 @@ impl MutURI {
         if let Some(ref host) = self.host() {
             string.push_str( host );
 
-            if let Some(port) = self.port() {
-                if let Some(ref scheme) = self.scheme() {
-                    if (scheme == HTTP && port != HTTP_PORT ) || (scheme == HTTPS && port != HTTPS_PORT ) {
-                        string.push( COLON );
-                        string.push_str( &port.to_string() );
-                    }
-                } else {
-                    string.push( COLON );
-                    string.push_str( &port.to_string() );
-                }
+            if let Some(port) = self.explicit_port() {
+                string.push( COLON );
+                string.push_str( &port.to_string() );
             }
         }
 
@@ -415,16 +830,9 @@ impl MutURI {
             if let Some(ref host) = self.host() {
                 string.push_str( host );
 
-                if let Some(port) = self.port() {
-                    if let Some(ref scheme) = self.scheme() {
-                        if (scheme == HTTP && port != HTTP_PORT ) || (scheme == HTTPS && port != HTTPS_PORT ) {
-                            string.push( COLON );
-                            string.push_str( &port.to_string() );
-                        }
-                    } else {
-                        string.push( COLON );
-                        string.push_str( &port.to_string() );
-                    }
+                if let Some(port) = self.explicit_port() {
+                    string.push( COLON );
+                    string.push_str( &port.to_string() );
                 }
             }
 
@@ -456,20 +864,13 @@ impl MutURI {
         if let Some(ref host) = self.host() {
             string.push_str( host );
 
-            if let Some(port) = self.mself().port {
-                if let Some(ref scheme) = self.scheme() {
-                    if (scheme == HTTP && port != HTTP_PORT ) || (scheme == HTTPS && port != HTTPS_PORT) {
-                        string.push( COLON );
-                        string.push_str( &port.to_string() );
-                    }
-                } else {
-                    string.push( COLON );
-                    string.push_str( &port.to_string() );
-                }
+            if let Some(port) = self.explicit_port() {
+                string.push( COLON );
+                string.push_str( &port.to_string() );
             }
         }
 
-        if let Some(ref path) = self.mself().path {
+        if let Some(ref path) = self.path_with_parameters() {
             string.push_str( path );
         }
 
@@ -497,8 +898,32 @@ impl MutURI {
         None
     }
 
+    fn public_suffix( &self ) -> Option<String> {
+        if self.is_ip_address() { return None }
+
+        if let Some(ref host) = self.mself().host {
+            return public_suffix::public_suffix( host )
+        }
+
+        None
+    }
+
+    fn registrable_domain( &self ) -> Option<String> {
+        if self.is_ip_address() { return None }
+
+        if let Some(ref host) = self.mself().host {
+            return public_suffix::registrable_domain( host )
+        }
+
+        None
+    }
+
     fn is_ip_address( &self ) -> bool {
         if let Some(ref host) = self.host() {
+            if host.starts_with( IPV6_OPEN ) {
+                return normalize_ipv6_literal( host ).is_some()
+            }
+
             return Ipv4Addr::from_str( host ).is_ok()
         }
 
@@ -536,10 +961,89 @@ impl MutURI {
         self.mself().host.clone()
     }
 
+    /// Host in its Unicode form, for reporting, the stored host is always ASCII.
+    fn unicode_host( &self ) -> Option<String> {
+        if let Some(host) = self.host() {
+            if host.starts_with( IPV6_OPEN ) { return Some(host) }
+
+            let (unicode, result) = idna::domain_to_unicode( &host );
+            return Some(if result.is_ok() { unicode } else { host })
+        }
+
+        None
+    }
+
     fn path( &self ) -> Option<String> {
         self.mself().path.clone()
     }
 
+    /// `path` with the matrix parameters put back in their segments.
+    fn path_with_parameters( &self ) -> Option<String> {
+        let mself = self.mself();
+        let path  = mself.path.as_ref()?;
+
+        if mself.path_parameters.is_empty() { return Some(path.clone()) }
+
+        let mut string = String::with_capacity( path.len() );
+        let mut index  = 0;
+
+        for (i, segment) in path.split( SLASH ).enumerate() {
+            if i > 0 { string.push( SLASH ) }
+            string.push_str( segment );
+
+            if segment.is_empty() { continue }
+
+            for &(_, ref name, ref value) in mself.path_parameters.iter().filter( |p| p.0 == index ) {
+                string.push( SEMICOLON );
+                string.push_str( &MutURI::encode_path( name ) );
+
+                if !value.is_empty() {
+                    string.push( EQUAL );
+                    string.push_str( &MutURI::encode_path( value ) );
+                }
+            }
+
+            index += 1;
+        }
+
+        Some(string)
+    }
+
+    /// Matrix parameters of all path segments as `[segment index, name, value]`, like
+    /// `[0, 'jsessionid', '1']` for `/app;jsessionid=1`.
+    ///
+    /// The index counts the non-empty segments of `path`, names may repeat.
+    fn path_parameters( &self ) -> Vec<(usize, String, String)> {
+        self.mself().path_parameters.clone()
+    }
+
+    /// Replaces all path parameters, in the format of `path_parameters`.
+    fn set_path_parameters( &self, parameters: Option<Vec<(usize, String, String)>> ) -> Result<(), Error> {
+        let parameters = parameters.unwrap_or_default();
+        let mut mself  = self.0.borrow_mut();
+
+        let segments = mself.path.as_ref().map_or( 0, |path|
+            path.split( SLASH ).filter( |s| !s.is_empty() ).count()
+        );
+
+        if let Some(&(index, ref name, _)) = parameters.iter().find( |p| p.0 >= segments ) {
+            return Err(Error::new(
+                magnus::exception::arg_error(),
+                format!( "No path segment {} for parameter '{}'", index, name )
+            ))
+        }
+
+        mself.path_parameters = parameters;
+        Ok(())
+    }
+
+    /// Copy of `self` without `SESSION_ID_PARAMETERS`.
+    fn without_session_ids( &self ) -> MutURI {
+        let stripped = self.dup();
+        stripped.0.borrow_mut().path_parameters.retain( |&(_, ref name, _)| !is_session_id( name ) );
+        stripped
+    }
+
     fn scheme( &self ) -> Option<String> {
         self.mself().scheme.clone()
     }
@@ -565,22 +1069,51 @@ impl MutURI {
     }
 
     fn dup( &self ) -> MutURI {
-        MutURI(std::cell::RefCell::new(
-            URI {
-                scheme:   self.scheme(),
-                userinfo: self.userinfo(),
-                host:     self.host(),
-                port:     self.port(),
-                path:     self.path(),
-                query:    self.query()
-            }
-        ))
+        MutURI(std::cell::RefCell::new( self.mself().clone() ))
     }
 
     fn ahash( &self ) -> u64 {
+        if HASH_WITHOUT_SESSION_IDS.load( Ordering::Relaxed ) &&
+            self.mself().path_parameters.iter().any( |&(_, ref name, _)| is_session_id( name ) ) {
+
+            return hash_obj( &self.without_session_ids().to_s() )
+        }
+
         hash_obj( &self.to_s() )
     }
 
+    /// Hash of the structure of the URL rather than its content.
+    ///
+    /// Parameter values and order are ignored and ID-like path segments are collapsed, so
+    /// `/item/1?id=1&p=2` and `/item/2?p=3&id=4` have the same shape.
+    fn shape_hash( &self ) -> u64 {
+        let mself = self.mself();
+
+        let path: Vec<&str> = mself.path.as_ref().map_or( vec![], |path|
+            path.split( SLASH ).map( |s| segment_shape( s ).unwrap_or( s ) ).collect()
+        );
+
+        let mut path_parameters: Vec<&String> = mself.path_parameters.iter().map( |p| &p.1 ).collect();
+        path_parameters.sort();
+        path_parameters.dedup();
+
+        let mut names: Vec<String> = query::pairs( mself.query.as_ref().map_or( "", |q| q.as_str() ) ).
+            into_iter().map( |(name, _)| name ).collect();
+        names.sort();
+        names.dedup();
+
+        hash_obj( &(&mself.scheme, &mself.host, mself.port, path, path_parameters, names) )
+    }
+
+    fn set_hash_without_session_ids( enabled: bool ) -> bool {
+        HASH_WITHOUT_SESSION_IDS.store( enabled, Ordering::Relaxed );
+        enabled
+    }
+
+    fn is_hash_without_session_ids() -> bool {
+        HASH_WITHOUT_SESSION_IDS.load( Ordering::Relaxed )
+    }
+
     fn inspect( &self ) -> String {
         format!( "#<SCNR::Engine::URIExt {}>", self.to_s() )
     }
@@ -621,50 +1154,73 @@ impl MutURI {
         muri.userinfo = None;
         muri.path     = None;
         muri.query    = None;
+        muri.path_parameters.clear();
     }
 
+    /// Resolves `self` against the `reference` base URL, as per RFC 3986 §5.2.2.
+    ///
+    /// Path parameters take part in the path merge, so `g;x` against `http://a/b/c/d;p?q` gives
+    /// `http://a/b/c/g;x`. An empty reference, like a fragment-only one, resolves to the base,
+    /// query included.
     fn as_absolute( &self, reference: &MutURI ) {
-        // println!( "{:?}", reference.inspect() );
+        let path  = self.path_with_parameters().unwrap_or_default();
+        let query = self.query();
 
-        let mut mself = self.0.borrow_mut();
+        let keep_dot_segments   = self.mself().keep_dot_segments;
+        let remove_dot_segments = |path: &str| {
+            if keep_dot_segments { path.to_string() } else { remove_dot_segments( path ) }
+        };
+
+        let (scheme, authority, path, query) = if self.scheme().is_some() {
+            (self.scheme(), self.authority(), remove_dot_segments( &path ), query)
 
-        // Complicated, delegate the path merge to the url crate.
-        if let Some(ref path) = mself.path.clone() {
+        } else if self.host().is_some() {
+            (reference.scheme(), self.authority(), remove_dot_segments( &path ), query)
 
-            let uri = Url::parse( &reference.to_s() );
-            if uri.is_err() {
-                mself.path = None;
+        } else {
+            let base_authority = reference.authority();
+            let base_path      = reference.path_with_parameters().unwrap_or_default();
+
+            let (path, query) = if path.is_empty() {
+                (base_path, query.or_else( || reference.query() ))
+            } else if path.starts_with( SLASH ) {
+                (remove_dot_segments( &path ), query)
             } else {
-                // println!( "{:?}", uri.clone().unwrap().join( path ) );
+                let merged = merge_paths( &base_path, base_authority.is_some(), &path );
+                (remove_dot_segments( &merged ), query)
+            };
 
-                let join = uri.clone().unwrap().join( path );
-                if join.is_err() {
-                    mself.path = None;
-                } else {
-                    mself.path = Some( join.unwrap().path().to_string())
-                }
-            }
+            (reference.scheme(), base_authority, path, query)
+        };
 
-            // That's an easy one, just use the reference path.
-        } else {
-            mself.path = reference.path();
-        }
+        let mut target = String::new();
 
-        if mself.scheme.is_none() {
-            mself.scheme = reference.scheme();
+        if let Some(ref scheme) = scheme {
+            target.push_str( scheme );
+            target.push( COLON );
         }
 
-        if mself.userinfo.is_none() {
-            mself.userinfo = reference.userinfo();
+        if let Some(ref authority) = authority {
+            target.push_str( DOUBLE_SLASH );
+            target.push_str( authority );
         }
 
-        if mself.host.is_none() {
-            mself.host = reference.host();
-        }
+        target.push_str( &path );
 
-        if mself.port.is_none() {
-            mself.port = reference.port();
+        if let Some(ref query) = query {
+            target.push( QUERY );
+            target.push_str( query );
         }
+
+        // Entities were decoded when `self` was parsed, `target` is made of its parts.
+        *self.0.borrow_mut() = URI::parse_with( target, !keep_dot_segments, false );
+    }
+
+    /// Rewrites `self` in place with the first of the `rules` that applies.
+    ///
+    /// Returns the index of the rule that fired, if any.
+    fn rewrite_bang( &self, rules: &rewrite::Rewrite ) -> Option<usize> {
+        rules.apply_to( self )
     }
 
     fn set_query( &self, query: Option<String> ) -> Option<String> {
@@ -701,7 +1257,7 @@ impl MutURI {
         let mut mself = self.0.borrow_mut();
 
         if let Some(ref h) = host {
-            mself.host = if h.is_empty() { None } else { host.clone() };
+            mself.host = if h.is_empty() { None } else { Some(normalize_host( h )) };
         } else {
             mself.host = host.clone();
         }
@@ -709,9 +1265,11 @@ impl MutURI {
         mself.host.clone()
     }
 
+    /// Path parameters belong to the segments of the old path, so they go along with it.
     fn set_path( &self, path: Option<String> ) -> Option<String> {
         let mut mself = self.0.borrow_mut();
         mself.path = path.clone();
+        mself.path_parameters.clear();
         path
     }
 
@@ -736,7 +1294,14 @@ pub fn initialize() -> Result<(), Error> {
     let class = engine_ns.define_class( "URIExt", class::object() )?;
 
     class.define_singleton_method( "new", function!(MutURI::new, 1) )?;
+    class.define_singleton_method( "with_dot_segments", function!(MutURI::with_dot_segments, 1) )?;
+    class.define_singleton_method( "strict", function!(MutURI::strict, 1) )?;
+    class.define_singleton_method( "_load", function!(MutURI::load, 1) )?;
+    class.define_singleton_method( "from_rpc_data", function!(MutURI::load, 1) )?;
     class.define_singleton_method( "decode", function!(MutURI::decode, 1) )?;
+    class.define_singleton_method( "to_absolute_all", function!(MutURI::to_absolute_all, 2) )?;
+    class.define_singleton_method( "hash_without_session_ids=", function!(MutURI::set_hash_without_session_ids, 1) )?;
+    class.define_singleton_method( "hash_without_session_ids?", function!(MutURI::is_hash_without_session_ids, 0) )?;
 
     class.define_method( "query", method!(MutURI::query, 0) )?;
     class.define_method( "query=", method!(MutURI::set_query, 1) )?;
@@ -749,14 +1314,21 @@ pub fn initialize() -> Result<(), Error> {
 
     class.define_method( "host", method!(MutURI::host, 0) )?;
     class.define_method( "host=", method!(MutURI::set_host, 1) )?;
+    class.define_method( "unicode_host", method!(MutURI::unicode_host, 0) )?;
 
     class.define_method( "path", method!(MutURI::path, 0) )?;
     class.define_method( "path=", method!(MutURI::set_path, 1) )?;
 
+    class.define_method( "path_parameters", method!(MutURI::path_parameters, 0) )?;
+    class.define_method( "path_parameters=", method!(MutURI::set_path_parameters, 1) )?;
+    class.define_method( "without_session_ids", method!(MutURI::without_session_ids, 0) )?;
+
     class.define_method( "scheme", method!(MutURI::scheme, 0) )?;
     class.define_method( "scheme=", method!(MutURI::set_scheme, 1) )?;
 
     class.define_method( "domain", method!(MutURI::domain, 0) )?;
+    class.define_method( "public_suffix", method!(MutURI::public_suffix, 0) )?;
+    class.define_method( "registrable_domain", method!(MutURI::registrable_domain, 0) )?;
     class.define_method( "user", method!(MutURI::user, 0) )?;
     class.define_method( "password", method!(MutURI::password, 0) )?;
 
@@ -778,13 +1350,27 @@ pub fn initialize() -> Result<(), Error> {
 
     class.define_method( "persistent_hash", method!(MutURI::ahash, 0) )?;
     class.define_method( "hash", method!(MutURI::ahash, 0) )?;
+    class.define_method( "shape_hash", method!(MutURI::shape_hash, 0) )?;
+
+    class.define_method( "_dump", method!(MutURI::marshal_dump, 1) )?;
+    class.define_method( "to_rpc_data", method!(MutURI::dump, 0) )?;
 
     class.define_method( "to_absolute!", method!(MutURI::as_absolute, 1) )?;
     class.define_method( "query_parameters", method!(MutURI::query_parameters, 0) )?;
+    class.define_method( "query_pairs", method!(MutURI::query_pairs, 0) )?;
+    class.define_method( "nested_query_parameters", method!(MutURI::nested_query_parameters, 0) )?;
+    class.define_method( "set_param", method!(MutURI::set_param, 2) )?;
+    class.define_method( "append_param", method!(MutURI::append_param, 2) )?;
+    class.define_method( "delete_param", method!(MutURI::delete_param, 1) )?;
+    class.define_method( "replace_param_value_at", method!(MutURI::replace_param_value_at, 2) )?;
+    class.define_method( "rewrite!", method!(MutURI::rewrite_bang, 1) )?;
 
     class.define_method( "inspect", method!(MutURI::inspect, 0) )?;
     class.define_method( "==", method!(MutURI::is_equal, 1) )?;
     class.define_method( "free", method!(MutURI::free, 0) )?;
 
-    Ok(())
+    scope::initialize()?;
+    rewrite::initialize()?;
+    template::initialize()?;
+    data_url::initialize()
 }
@@ -0,0 +1,221 @@
+//! `data:` URLs, which `Engine::URIExt` treats as empty.
+//!
+//! Parsing follows the WHATWG Fetch Standard's `data:` URL processor, so the defaults match
+//! what browsers do, like `text/plain;charset=US-ASCII` for a missing MIME type.
+
+use url::percent_encoding;
+use magnus::{class, function, method, Error, RHash, RModule, RString, prelude::*};
+
+const SCHEME:    &str = "data:";
+const BASE64:    &str = "base64";
+const CHARSET:   &str = "charset";
+const COMMA:     char = ',';
+const SEMICOLON: char = ';';
+const EQUAL:     char = '=';
+const SLASH:     char = '/';
+const FRAG:      char = '#';
+const PAD:       u8   = b'=';
+
+const DEFAULT_MIME_TYPE: &str = "text/plain";
+const DEFAULT_CHARSET:   &str = "US-ASCII";
+
+/// MIME types of documents that may contain further paths.
+const DOCUMENT_MIME_TYPES: [&str; 3] = ["text/html", "application/xhtml+xml", "image/svg+xml"];
+
+/// Value of the base64 `c` character, `None` for characters outside the alphabet.
+fn base64_value( c: u8 ) -> Option<u8> {
+    match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+'        => Some(62),
+        b'/'        => Some(63),
+        _           => None
+    }
+}
+
+/// Forgiving base64 decode, ASCII whitespace is ignored and padding is optional.
+fn base64_decode( input: &[u8] ) -> Option<Vec<u8>> {
+    let mut data: Vec<u8> = input.iter().cloned().filter( |c| !c.is_ascii_whitespace() ).collect();
+
+    if data.len() % 4 == 0 {
+        for _ in 0..2 {
+            if data.last() == Some(&PAD) { data.pop(); }
+        }
+    }
+
+    if data.len() % 4 == 1 { return None }
+
+    let mut decoded = Vec::with_capacity( data.len() * 3 / 4 );
+    let mut buffer: u32 = 0;
+    let mut bits        = 0;
+
+    for c in data {
+        buffer = (buffer << 6) | base64_value( c )? as u32;
+        bits  += 6;
+
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push( (buffer >> bits) as u8 );
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    Some(decoded)
+}
+
+fn is_token( s: &str ) -> bool {
+    !s.is_empty() && s.bytes().all( |b|
+        b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains( &b )
+    )
+}
+
+/// Splits `type/subtype;name=value` into its lowercase essence and parameters, `None` if the
+/// type is not valid.
+fn parse_mime_type( mime_type: &str ) -> Option<(String, Vec<(String, String)>)> {
+    let mut parts = mime_type.split( SEMICOLON );
+    let essence   = parts.next()?.trim().to_lowercase();
+
+    {
+        let mut split = essence.splitn( 2, SLASH );
+        if !is_token( split.next()? ) || !is_token( split.next()? ) { return None }
+    }
+
+    let mut parameters: Vec<(String, String)> = vec![];
+    for parameter in parts {
+        let mut pair = parameter.splitn( 2, EQUAL );
+        let name     = pair.next().unwrap_or_default().trim().to_lowercase();
+        let value    = pair.next().unwrap_or_default().trim().trim_matches( '"' ).to_string();
+
+        if !is_token( &name ) || value.is_empty() { continue }
+        if parameters.iter().any( |&(ref n, _)| n == &name ) { continue }
+
+        parameters.push( (name, value) );
+    }
+
+    Some((essence, parameters))
+}
+
+#[magnus::wrap(class = "SCNR::Engine::Rust::URI::DataURL", free_immediately, size)]
+#[derive(Debug, PartialEq)]
+pub struct DataURL {
+    mime_type:  String,
+    parameters: Vec<(String, String)>,
+    base64:     bool,
+    data:       Vec<u8>
+}
+
+impl DataURL {
+
+    /// Returns `None` if `url` is not a valid `data:` URL.
+    pub fn parse( url: &str ) -> Option<Self> {
+        let url = url.trim();
+
+        if !url.get( ..SCHEME.len() )?.eq_ignore_ascii_case( SCHEME ) { return None }
+
+        let url = url[SCHEME.len()..].split( FRAG ).next().unwrap_or_default();
+
+        let comma         = url.find( COMMA )?;
+        let mut mime_type = url[..comma].trim();
+        let body          = percent_encoding::percent_decode( url[comma + 1..].as_bytes() ).collect::<Vec<u8>>();
+
+        // `;base64` has to come last, possibly with some whitespace around.
+        let mut base64 = false;
+        if let Some(i) = mime_type.rfind( SEMICOLON ) {
+            if mime_type[i + 1..].trim().eq_ignore_ascii_case( BASE64 ) {
+                base64    = true;
+                mime_type = &mime_type[..i];
+            }
+        }
+
+        let data = if base64 { base64_decode( &body )? } else { body };
+
+        let mime_type = if mime_type.starts_with( SEMICOLON ) {
+            format!( "{}{}", DEFAULT_MIME_TYPE, mime_type )
+        } else {
+            mime_type.to_string()
+        };
+
+        let (mime_type, parameters) = parse_mime_type( &mime_type ).unwrap_or_else( ||
+            (
+                DEFAULT_MIME_TYPE.to_string(),
+                vec![(CHARSET.to_string(), DEFAULT_CHARSET.to_string())]
+            )
+        );
+
+        Some(DataURL {
+            mime_type:  mime_type,
+            parameters: parameters,
+            base64:     base64,
+            data:       data
+        })
+    }
+
+    fn new( url: String ) -> Result<Self, Error> {
+        DataURL::parse( &url ).ok_or_else( ||
+            Error::new( magnus::exception::arg_error(), format!( "Invalid data: URL: {}", url ) )
+        )
+    }
+
+    fn parse_ext( url: String ) -> Option<Self> {
+        DataURL::parse( &url )
+    }
+
+    /// Lowercase `type/subtype`, without parameters.
+    fn mime_type( &self ) -> String {
+        self.mime_type.clone()
+    }
+
+    pub fn charset( &self ) -> Option<String> {
+        self.parameters.iter().
+            find( |&&(ref name, _)| name == CHARSET ).
+            map( |&(_, ref value)| value.clone() )
+    }
+
+    fn parameters( &self ) -> Result<RHash, Error> {
+        let hash = RHash::new();
+        for &(ref name, ref value) in &self.parameters {
+            hash.aset( name.as_str(), value.as_str() )?;
+        }
+        Ok(hash)
+    }
+
+    fn is_base64( &self ) -> bool {
+        self.base64
+    }
+
+    /// Whether the payload is a document that may contain further paths, like HTML or SVG.
+    pub fn is_document( &self ) -> bool {
+        DOCUMENT_MIME_TYPES.contains( &self.mime_type.as_str() )
+    }
+
+    /// Decoded payload, as binary.
+    fn data( &self ) -> RString {
+        RString::from_slice( &self.data )
+    }
+
+    fn size( &self ) -> usize {
+        self.data.len()
+    }
+}
+
+pub fn initialize() -> Result<(), Error> {
+    let scnr_ns = class::object().const_get::<_, RModule>("SCNR")?;
+    let engine_ns = scnr_ns.const_get::<_, RModule>("Engine")?;
+    let rust_ns = engine_ns.define_module("Rust")?;
+    let uri_ns = rust_ns.define_module("URI")?;
+    let data_url_class = uri_ns.define_class("DataURL", class::object())?;
+
+    data_url_class.define_singleton_method("new", function!(DataURL::new, 1))?;
+    data_url_class.define_singleton_method("parse", function!(DataURL::parse_ext, 1))?;
+
+    data_url_class.define_method("mime_type", method!(DataURL::mime_type, 0))?;
+    data_url_class.define_method("charset", method!(DataURL::charset, 0))?;
+    data_url_class.define_method("parameters", method!(DataURL::parameters, 0))?;
+    data_url_class.define_method("base64?", method!(DataURL::is_base64, 0))?;
+    data_url_class.define_method("document?", method!(DataURL::is_document, 0))?;
+    data_url_class.define_method("data", method!(DataURL::data, 0))?;
+    data_url_class.define_method("size", method!(DataURL::size, 0))?;
+
+    Ok(())
+}
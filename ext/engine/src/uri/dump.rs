@@ -0,0 +1,160 @@
+//! Binary serialization of `URI` fields, for `Engine::URIExt#_dump` and `#to_rpc_data`.
+//!
+//! Layout, integers are little-endian:
+//!
+//! * `u8` format version.
+//! * `u8` bitmask of present fields, in `FIELDS` order, and of `KEEP_DOT_SEGMENTS`.
+//! * Present fields, strings as a `u32` length followed by UTF-8 bytes, the port as `u16`.
+//! * `u32` number of path parameters, each as a `u32` segment index and 2 strings.
+//!
+//! Bump `VERSION` whenever the layout changes.
+
+use uri::URI;
+
+const VERSION: u8 = 1;
+
+const SCHEME:   u8 = 1;
+const USERINFO: u8 = 1 << 1;
+const HOST:     u8 = 1 << 2;
+const PORT:     u8 = 1 << 3;
+const PATH:     u8 = 1 << 4;
+const QUERY:    u8 = 1 << 5;
+
+const KEEP_DOT_SEGMENTS: u8 = 1 << 6;
+
+fn write_u32( data: &mut Vec<u8>, n: u32 ) {
+    data.extend_from_slice( &n.to_le_bytes() );
+}
+
+fn write_str( data: &mut Vec<u8>, s: &str ) {
+    write_u32( data, s.len() as u32 );
+    data.extend_from_slice( s.as_bytes() );
+}
+
+fn write_opt_str( data: &mut Vec<u8>, s: &Option<String> ) {
+    if let Some(ref s) = *s {
+        write_str( data, s );
+    }
+}
+
+pub(super) fn dump( uri: &URI ) -> Vec<u8> {
+    let mut flags = 0;
+    if uri.scheme.is_some()   { flags |= SCHEME }
+    if uri.userinfo.is_some() { flags |= USERINFO }
+    if uri.host.is_some()     { flags |= HOST }
+    if uri.port.is_some()     { flags |= PORT }
+    if uri.path.is_some()     { flags |= PATH }
+    if uri.query.is_some()    { flags |= QUERY }
+    if uri.keep_dot_segments  { flags |= KEEP_DOT_SEGMENTS }
+
+    let mut data = Vec::with_capacity( 64 );
+    data.push( VERSION );
+    data.push( flags );
+
+    write_opt_str( &mut data, &uri.scheme );
+    write_opt_str( &mut data, &uri.userinfo );
+    write_opt_str( &mut data, &uri.host );
+
+    if let Some(port) = uri.port {
+        data.extend_from_slice( &port.to_le_bytes() );
+    }
+
+    write_opt_str( &mut data, &uri.path );
+    write_opt_str( &mut data, &uri.query );
+
+    write_u32( &mut data, uri.path_parameters.len() as u32 );
+    for &(index, ref name, ref value) in &uri.path_parameters {
+        write_u32( &mut data, index as u32 );
+        write_str( &mut data, name );
+        write_str( &mut data, value );
+    }
+
+    data
+}
+
+struct Reader<'a> {
+    data:     &'a [u8],
+    position: usize
+}
+
+impl<'a> Reader<'a> {
+
+    fn take( &mut self, length: usize ) -> Result<&'a [u8], String> {
+        let end = self.position.checked_add( length ).
+            filter( |&end| end <= self.data.len() ).
+            ok_or_else( || "unexpected end of data".to_string() )?;
+
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn u8( &mut self ) -> Result<u8, String> {
+        Ok(self.take( 1 )?[0])
+    }
+
+    fn u16( &mut self ) -> Result<u16, String> {
+        let bytes = self.take( 2 )?;
+        Ok(u16::from_le_bytes( [bytes[0], bytes[1]] ))
+    }
+
+    fn u32( &mut self ) -> Result<u32, String> {
+        let bytes = self.take( 4 )?;
+        Ok(u32::from_le_bytes( [bytes[0], bytes[1], bytes[2], bytes[3]] ))
+    }
+
+    fn string( &mut self ) -> Result<String, String> {
+        let length = self.u32()? as usize;
+        let bytes  = self.take( length )?;
+
+        String::from_utf8( bytes.to_vec() ).map_err( |e| e.to_string() )
+    }
+
+    fn opt_string( &mut self, present: bool ) -> Result<Option<String>, String> {
+        if !present { return Ok(None) }
+        Ok(Some(self.string()?))
+    }
+}
+
+/// Restores a `URI` from `dump` data, returns the reason if the data is not valid.
+pub(super) fn load( data: &[u8] ) -> Result<URI, String> {
+    let mut reader = Reader { data: data, position: 0 };
+
+    let version = reader.u8()?;
+    if version != VERSION {
+        return Err(format!( "unsupported format version {}", version ))
+    }
+
+    let flags = reader.u8()?;
+
+    let scheme   = reader.opt_string( flags & SCHEME != 0 )?;
+    let userinfo = reader.opt_string( flags & USERINFO != 0 )?;
+    let host     = reader.opt_string( flags & HOST != 0 )?;
+    let port     = if flags & PORT != 0 { Some(reader.u16()?) } else { None };
+    let path     = reader.opt_string( flags & PATH != 0 )?;
+    let query    = reader.opt_string( flags & QUERY != 0 )?;
+
+    let count = reader.u32()? as usize;
+
+    // Don't trust the count for the allocation, every parameter takes at least 12 bytes.
+    let mut path_parameters = Vec::with_capacity( count.min( data.len() / 12 ) );
+    for _ in 0..count {
+        let index = reader.u32()? as usize;
+        path_parameters.push( (index, reader.string()?, reader.string()?) );
+    }
+
+    if reader.position != data.len() {
+        return Err("trailing data".to_string())
+    }
+
+    Ok(URI {
+        scheme:            scheme,
+        userinfo:          userinfo,
+        host:              host,
+        port:              port,
+        path:              path,
+        query:             query,
+        path_parameters:   path_parameters,
+        keep_dot_segments: flags & KEEP_DOT_SEGMENTS != 0
+    })
+}
@@ -0,0 +1,254 @@
+//! Query string helpers for `Engine::URIExt`.
+
+use magnus::{Error, RArray, RHash, RString, Value, prelude::*};
+
+use uri::{MutURI, AMP, EQUAL, AMP_S};
+
+const OPEN_BRACKET:  char = '[';
+const CLOSE_BRACKET: char = ']';
+
+/// How many levels deep names may nest, like Rack's `param_depth_limit`.
+const MAX_DEPTH: usize = 100;
+
+/// Decoded name/value pairs of `query`, in order and with duplicates.
+pub fn pairs( query: &str ) -> Vec<(String, String)> {
+    let mut pairs = vec![];
+
+    for pair in query.split( AMP ) {
+        if pair.is_empty() { continue }
+
+        let mut pair_iter = pair.splitn( 2, EQUAL );
+        let name  = MutURI::decode( pair_iter.next().unwrap_or_default().to_string() );
+        let value = MutURI::decode( pair_iter.next().unwrap_or_default().to_string() );
+
+        pairs.push( (name, value) );
+    }
+
+    pairs
+}
+
+fn raw_name( segment: &str ) -> &str {
+    segment.splitn( 2, EQUAL ).next().unwrap_or_default()
+}
+
+fn is_named( segment: &str, name: &str ) -> bool {
+    !segment.is_empty() && MutURI::decode( raw_name( segment ).to_string() ) == name
+}
+
+/// `segment` with its value replaced, the name keeps its original bytes.
+fn with_value( segment: &str, value: &str ) -> String {
+    let mut pair = raw_name( segment ).to_string();
+    pair.push( EQUAL );
+    pair.push_str( &MutURI::encode_query( value ) );
+    pair
+}
+
+fn new_pair( name: &str, value: &str ) -> String {
+    let mut pair = MutURI::encode_query( name );
+    pair.push( EQUAL );
+    pair.push_str( &MutURI::encode_query( value ) );
+    pair
+}
+
+/// Sets the value of the first `name` parameter in the raw `query` and removes any duplicates,
+/// or appends it if there is none.
+///
+/// The rest of the query keeps its bytes, only `name` and `value` get encoded.
+pub fn set( query: &str, name: &str, value: &str ) -> String {
+    if query.is_empty() { return new_pair( name, value ) }
+
+    let mut found    = false;
+    let mut segments = vec![];
+
+    for segment in query.split( AMP ) {
+        if !is_named( segment, name ) {
+            segments.push( segment.to_string() );
+            continue
+        }
+
+        if found { continue }
+        found = true;
+
+        segments.push( with_value( segment, value ) );
+    }
+
+    if !found { segments.push( new_pair( name, value ) ) }
+
+    segments.join( AMP_S )
+}
+
+/// Appends a `name` parameter to the raw `query`, even if one already exists.
+pub fn append( query: &str, name: &str, value: &str ) -> String {
+    if query.is_empty() { return new_pair( name, value ) }
+
+    let mut query = query.to_string();
+    query.push( AMP );
+    query.push_str( &new_pair( name, value ) );
+    query
+}
+
+/// Removes all `name` parameters from the raw `query`.
+pub fn delete( query: &str, name: &str ) -> String {
+    query.split( AMP ).
+        filter( |segment| !is_named( segment, name ) ).
+        collect::<Vec<&str>>().
+        join( AMP_S )
+}
+
+/// Replaces the value of the parameter at `index`, as ordered by `pairs`, in the raw `query`.
+///
+/// Returns `None` if there is no such parameter.
+pub fn replace_value_at( query: &str, index: usize, value: &str ) -> Option<String> {
+    let mut segments: Vec<String> = query.split( AMP ).map( |s| s.to_string() ).collect();
+
+    let position = segments.iter().
+        enumerate().
+        filter( |&(_, segment)| !segment.is_empty() ).
+        nth( index )?.0;
+
+    segments[position] = with_value( &segments[position], value );
+    Some(segments.join( AMP_S ))
+}
+
+/// A PHP/Rails-style nested parameter.
+#[derive(Debug, PartialEq)]
+pub enum Param {
+    Value( String ),
+    List( Vec<Param> ),
+    Map( Vec<(String, Param)> )
+}
+
+impl Param {
+    fn to_value( &self ) -> Result<Value, Error> {
+        Ok(match *self {
+            Param::Value( ref value ) => RString::new( value ).as_value(),
+
+            Param::List( ref list ) => {
+                let array = RArray::new();
+                for param in list {
+                    array.push( param.to_value()? )?;
+                }
+                array.as_value()
+            },
+
+            Param::Map( ref map ) => {
+                let hash = RHash::new();
+                for &(ref name, ref param) in map {
+                    hash.aset( RString::new( name ), param.to_value()? )?;
+                }
+                hash.as_value()
+            }
+        })
+    }
+}
+
+/// Splits `a[b][]` into `a` and `["b", ""]`.
+///
+/// Names with unbalanced brackets, or nested deeper than `MAX_DEPTH`, are treated as plain
+/// names, without subkeys.
+fn split_name( name: &str ) -> (String, Vec<String>) {
+    let open = match name.find( OPEN_BRACKET ) {
+        Some(i) if i > 0 => i,
+        _                => return (name.to_string(), vec![])
+    };
+
+    let mut subkeys = vec![];
+    let mut rest    = &name[open..];
+
+    while rest.starts_with( OPEN_BRACKET ) {
+        if subkeys.len() == MAX_DEPTH { return (name.to_string(), vec![]) }
+
+        let close = match rest.find( CLOSE_BRACKET ) {
+            Some(i) => i,
+            None    => return (name.to_string(), vec![])
+        };
+
+        subkeys.push( rest[1..close].to_string() );
+        rest = &rest[close + 1..];
+    }
+
+    if !rest.is_empty() { return (name.to_string(), vec![]) }
+
+    (name[..open].to_string(), subkeys)
+}
+
+fn entry<'a>( map: &'a mut Vec<(String, Param)>, key: &str, default: fn() -> Param ) -> &'a mut Param {
+    let index = match map.iter().position( |&(ref k, _)| k == key ) {
+        Some(i) => i,
+        None    => {
+            map.push( (key.to_string(), default()) );
+            map.len() - 1
+        }
+    };
+
+    &mut map[index].1
+}
+
+fn new_list() -> Param { Param::List( vec![] ) }
+fn new_map()  -> Param { Param::Map( vec![] ) }
+
+fn has_key( param: &Param, key: &str ) -> bool {
+    if let Param::Map( ref map ) = *param {
+        return map.iter().any( |&(ref k, _)| k == key )
+    }
+
+    false
+}
+
+fn insert( map: &mut Vec<(String, Param)>, key: &str, subkeys: &[String], value: String ) {
+    if subkeys.is_empty() {
+        *entry( map, key, new_list ) = Param::Value( value );
+        return
+    }
+
+    let param = entry( map, key, if subkeys[0].is_empty() { new_list } else { new_map } );
+
+    if subkeys[0].is_empty() {
+        if let Param::Map(..) | Param::Value(..) = *param { *param = new_list() }
+
+        if let Param::List( ref mut list ) = *param {
+            if subkeys.len() == 1 {
+                list.push( Param::Value( value ) );
+                return
+            }
+
+            // `a[][b]=1&a[][c]=2` fills the same map until a key repeats.
+            let child_key = &subkeys[1];
+            let reuse     = match list.last() {
+                Some(last @ &Param::Map(..)) => !has_key( last, child_key ),
+                _                            => false
+            };
+
+            if !reuse { list.push( new_map() ) }
+
+            if let Some(&mut Param::Map( ref mut child )) = list.last_mut() {
+                insert( child, child_key, &subkeys[2..], value );
+            }
+        }
+
+        return
+    }
+
+    if let Param::List(..) | Param::Value(..) = *param { *param = new_map() }
+
+    if let Param::Map( ref mut child ) = *param {
+        insert( child, &subkeys[0], &subkeys[1..], value );
+    }
+}
+
+/// Structures `pairs` based on PHP/Rails-style nested names, so `a[b][]=1&a[b][]=2` becomes
+/// `{ "a" => { "b" => ["1", "2"] } }`.
+pub fn nested( pairs: &[(String, String)] ) -> Param {
+    let mut map = vec![];
+
+    for &(ref name, ref value) in pairs {
+        let (key, subkeys) = split_name( name );
+        insert( &mut map, &key, &subkeys, value.clone() );
+    }
+
+    Param::Map( map )
+}
+
+pub fn nested_value( pairs: &[(String, String)] ) -> Result<Value, Error> {
+    nested( pairs ).to_value()
+}
@@ -0,0 +1,176 @@
+//! Corresponds to `Engine::URICommon#rewrite`.
+//!
+//! Compiles `OptionGroups::Scope#url_rewrites` once so that `Engine::URIExt` objects can be
+//! rewritten in place without a round-trip through Ruby regular expressions, unless a pattern
+//! needs one.
+
+use magnus::{class, function, method, Error, RHash, RModule, RString, Value, prelude::*};
+use magnus::r_hash::ForEach;
+use magnus::value::BoxValue;
+
+use uri::{MutURI, URI};
+use uri::scope::Pattern;
+
+const BACKSLASH: char = '\\';
+const DOLLAR:    char = '$';
+const NAMED_OPEN:  &str = "k<";
+const NAMED_CLOSE: char = '>';
+
+/// Converts a Ruby `String#gsub` substitution (`\1`, `\k<name>`, `\0`, `\&`) to the `regex`
+/// crate's `${1}` syntax, escaping literal `$` along the way.
+fn to_replacement( substitution: &str ) -> String {
+    let mut replacement = String::with_capacity( substitution.len() );
+    let mut chars       = substitution.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        if c == DOLLAR {
+            replacement.push_str( "$$" );
+            continue
+        }
+
+        if c != BACKSLASH {
+            replacement.push( c );
+            continue
+        }
+
+        match chars.peek().map( |&(_, next)| next ) {
+            Some(d) if d.is_ascii_digit() => {
+                chars.next();
+                replacement.push_str( &format!( "${{{}}}", d ) );
+            },
+
+            Some('&') => {
+                chars.next();
+                replacement.push_str( "${0}" );
+            },
+
+            Some(BACKSLASH) => {
+                chars.next();
+                replacement.push( BACKSLASH );
+            },
+
+            Some('k') if substitution[i + 1..].starts_with( NAMED_OPEN ) => {
+                let rest = &substitution[i + 1 + NAMED_OPEN.len()..];
+
+                if let Some(close) = rest.find( NAMED_CLOSE ) {
+                    replacement.push_str( &format!( "${{{}}}", &rest[..close] ) );
+
+                    // Skip over `k<name>`.
+                    for _ in 0..NAMED_OPEN.len() + rest[..close].chars().count() + 1 {
+                        chars.next();
+                    }
+                } else {
+                    replacement.push( c );
+                }
+            },
+
+            _ => replacement.push( c )
+        }
+    }
+
+    replacement
+}
+
+struct Rule {
+    pattern:      Pattern,
+    key:          BoxValue<Value>,
+    substitution: String,
+    replacement:  String
+}
+
+impl Rule {
+    fn replace_all( &self, url: &str ) -> Option<String> {
+        match self.pattern {
+            Pattern::Native( ref regex ) =>
+                Some(regex.replace_all( url, self.replacement.as_str() ).into_owned()),
+
+            Pattern::Ruby( ref regexp ) =>
+                RString::new( url ).funcall( "gsub", (**regexp, self.substitution.as_str()) ).ok()
+        }
+    }
+}
+
+#[magnus::wrap(class = "SCNR::Engine::Rust::URI::Rewrite", free_immediately, size)]
+pub struct Rewrite {
+    rules: Vec<Rule>
+}
+
+impl Rewrite {
+
+    /// Compiles `rules`, pattern and substitution pairs in order of priority, like
+    /// `OptionGroups::Scope#url_rewrites`; patterns may also be plain strings, like
+    /// `#to_rpc_data` has them.
+    fn new( rules: RHash ) -> Result<Self, Error> {
+        let mut pairs = vec![];
+        rules.foreach( |key: Value, substitution: String| {
+            pairs.push( (key, substitution) );
+            Ok(ForEach::Continue)
+        })?;
+
+        let mut compiled = vec![];
+        for (key, substitution) in pairs {
+            compiled.push( Rule {
+                pattern:      Pattern::new( key )?,
+                key:          BoxValue::new( key ),
+                replacement:  to_replacement( &substitution ),
+                substitution: substitution
+            });
+        }
+
+        Ok(Rewrite { rules: compiled })
+    }
+
+    /// Rewrites `url` with the first rule that changes it.
+    ///
+    /// Returns the rewritten URL and the index of the rule that fired.
+    pub fn apply( &self, url: &str ) -> Option<(String, usize)> {
+        for (i, rule) in self.rules.iter().enumerate() {
+            let rewritten = match rule.replace_all( url ) {
+                Some(rewritten) => rewritten,
+                None            => continue
+            };
+
+            if rewritten != url {
+                return Some((rewritten, i))
+            }
+        }
+
+        None
+    }
+
+    /// Rewrites `url` in place.
+    ///
+    /// Returns the index of the rule that fired, if any.
+    pub fn apply_to( &self, url: &MutURI ) -> Option<usize> {
+        let (rewritten, i)    = self.apply( &url.to_s() )?;
+        let keep_dot_segments = url.0.borrow().keep_dot_segments;
+
+        // Entities were decoded when `url` was parsed, `rewritten` is made of its parts.
+        *url.0.borrow_mut() = URI::parse_with( rewritten, !keep_dot_segments, false );
+        Some(i)
+    }
+
+    fn rules( &self ) -> Vec<(Value, String)> {
+        self.rules.iter().map( |r| (*r.key, r.substitution.clone()) ).collect()
+    }
+
+    fn size( &self ) -> usize {
+        self.rules.len()
+    }
+}
+
+pub fn initialize() -> Result<(), Error> {
+    let scnr_ns = class::object().const_get::<_, RModule>("SCNR")?;
+    let engine_ns = scnr_ns.const_get::<_, RModule>("Engine")?;
+    let rust_ns = engine_ns.define_module("Rust")?;
+    let uri_ns = rust_ns.define_module("URI")?;
+    let rewrite_class = uri_ns.define_class("Rewrite", class::object())?;
+
+    rewrite_class.define_singleton_method("new", function!(Rewrite::new, 1))?;
+
+    rewrite_class.define_method("apply", method!(Rewrite::apply_to, 1))?;
+    rewrite_class.define_method("rules", method!(Rewrite::rules, 0))?;
+    rewrite_class.define_method("size", method!(Rewrite::size, 0))?;
+
+    Ok(())
+}
@@ -0,0 +1,335 @@
+//! Corresponds to `Engine::URICommon::Scope`.
+//!
+//! Evaluates the option-driven scope checks for `Engine::URIExt` objects in one native call,
+//! the `URICommon::Scope.select`/`.reject` decision hooks remain Ruby-only.
+
+use std::collections::{HashMap, HashSet};
+
+use regex::{Regex, RegexBuilder};
+use magnus::{class, function, method, Error, RArray, RHash, RModule, RRegexp, RString, Value, prelude::*};
+use magnus::r_hash::ForEach;
+use magnus::r_regexp::Opts;
+use magnus::value::BoxValue;
+
+use uri::{MutURI, is_secure, HTTPS, SLASH};
+
+// `OptionGroups::Scope#to_rpc_data` keys.
+const URL:                     &str = "url";
+const INCLUDE_SUBDOMAINS:      &str = "include_subdomains";
+const REGISTRABLE_DOMAINS:     &str = "registrable_domains";
+const HTTPS_ONLY:              &str = "https_only";
+const DEPTH_LIMIT:             &str = "depth_limit";
+const DIRECTORY_DEPTH_LIMIT:   &str = "directory_depth_limit";
+const INCLUDE_PATH_PATTERNS:   &str = "include_path_patterns";
+const EXCLUDE_PATH_PATTERNS:   &str = "exclude_path_patterns";
+const EXCLUDE_FILE_EXTENSIONS: &str = "exclude_file_extensions";
+const REDUNDANT_PATH_PATTERNS: &str = "redundant_path_patterns";
+const AUTO_REDUNDANT_PATHS:    &str = "auto_redundant_paths";
+const AUTO_REDUNDANT_SHAPES:   &str = "auto_redundant_shapes";
+
+// `OptionGroups::Scope#to_native_data` only.
+const AUTO_REDUNDANT_COUNTER:       &str = "auto_redundant_counter";
+const AUTO_REDUNDANT_SHAPE_COUNTER: &str = "auto_redundant_shape_counter";
+
+/// A scope or rewrite pattern, matched natively unless it uses syntax only Ruby understands.
+pub enum Pattern {
+    Native( Regex ),
+
+    /// Lookarounds, backreferences, `\h` and the like.
+    Ruby( BoxValue<RRegexp> )
+}
+
+impl Pattern {
+    /// `Regexp`s keep their flags, strings are compiled case-insensitively like
+    /// `OptionGroups::Scope` does with them.
+    pub fn new( pattern: Value ) -> Result<Self, Error> {
+        let regexp = match RRegexp::from_value( pattern ) {
+            Some(regexp) => regexp,
+            None         => RRegexp::new( &String::try_convert( pattern )?, Opts::IGNORECASE )?
+        };
+
+        let source: String = regexp.funcall( "source", () )?;
+        let options        = regexp.options();
+
+        // Ruby's `^` and `$` always match at line boundaries, its multiline mode is about `.`.
+        let compiled = RegexBuilder::new( &source ).
+            multi_line( true ).
+            case_insensitive( options.is_ignorecase() ).
+            ignore_whitespace( options.is_extend() ).
+            dot_matches_new_line( options.is_multiline() ).
+            build();
+
+        Ok(match compiled {
+            Ok(regex) => Pattern::Native( regex ),
+            Err(_)    => Pattern::Ruby( BoxValue::new( regexp ) )
+        })
+    }
+
+    pub fn is_match( &self, s: &str ) -> bool {
+        match *self {
+            Pattern::Native( ref regex ) => regex.is_match( s ),
+            Pattern::Ruby( ref regexp )  => regexp.reg_match( s ).ok().map_or( false, |m| m.is_some() )
+        }
+    }
+}
+
+fn compile_all( patterns: Option<RArray> ) -> Result<Vec<Pattern>, Error> {
+    let patterns = match patterns {
+        // Copy the elements out before calling back into Ruby.
+        Some(patterns) => unsafe { patterns.as_slice() }.to_vec(),
+        None           => return Ok(vec![])
+    };
+
+    patterns.into_iter().map( Pattern::new ).collect()
+}
+
+/// Whether `key` has been counted `limit` times, counting it once more if not and
+/// `update_counters`.
+fn reached<K: IntoValue + Copy>( counters: RHash, key: K, limit: i64, update_counters: bool ) -> Result<bool, Error> {
+    let count = counters.aref::<_, Option<i64>>( key )?.unwrap_or( 0 );
+    if count >= limit { return Ok(true) }
+
+    if update_counters {
+        counters.aset( key, count + 1 )?;
+    }
+
+    Ok(false)
+}
+
+/// The key `URICommon::Scope#auto_redundant?` counts `url` under.
+fn auto_redundant_key( url: &MutURI, params: &HashMap<String, String> ) -> Result<i64, Error> {
+    let mut names: Vec<String> = params.keys().cloned().collect();
+    names.sort();
+
+    let names: String = RArray::from_vec( names ).funcall( "to_s", () )?;
+    RString::new( &format!( "{}{}", url.without_query(), names ) ).funcall( "hash", () )
+}
+
+#[magnus::wrap(class = "SCNR::Engine::Rust::URI::Scope", free_immediately, size)]
+pub struct Scope {
+    reference:               Option<MutURI>,
+    include_subdomains:      bool,
+    registrable_domains:     bool,
+    https_only:              bool,
+    depth_limit:             Option<i64>,
+    directory_depth_limit:   Option<i64>,
+    include_path_patterns:   Vec<Pattern>,
+    exclude_path_patterns:   Vec<Pattern>,
+    exclude_file_extensions: HashSet<String>,
+
+    // The counters are the Ruby options' own hashes, so that `URICommon::Scope` and this
+    // count the same URLs.
+
+    /// Patterns along with their keys in `redundant_counters`.
+    redundant_path_patterns: Vec<(Pattern, BoxValue<Value>)>,
+    redundant_counters:      BoxValue<RHash>,
+    auto_redundant_paths:    Option<i64>,
+    auto_redundant_counter:  BoxValue<RHash>,
+    auto_redundant_shapes:   Option<i64>,
+    shape_counter:           BoxValue<RHash>
+}
+
+impl Scope {
+
+    /// Compiles `options`, as returned by `OptionGroups::Scope#to_native_data` with the seed URL
+    /// added under `url`; patterns may also be plain strings, like `#to_rpc_data` has them, in
+    /// which case the counters start over.
+    fn new( options: RHash ) -> Result<Self, Error> {
+        let counters = |key| -> Result<RHash, Error> {
+            Ok(options.lookup::<_, Option<RHash>>( key )?.unwrap_or_else( RHash::new ))
+        };
+
+        let redundant_counters = counters( REDUNDANT_PATH_PATTERNS )?;
+
+        let mut keys = vec![];
+        redundant_counters.foreach( |key: Value, _: Value| {
+            keys.push( key );
+            Ok(ForEach::Continue)
+        })?;
+
+        let mut redundant_path_patterns = vec![];
+        for key in keys {
+            redundant_path_patterns.push( (Pattern::new( key )?, BoxValue::new( key )) );
+        }
+
+        let exclude_file_extensions = options.
+            lookup::<_, Option<Vec<String>>>( EXCLUDE_FILE_EXTENSIONS )?.
+            unwrap_or_default().iter().map( |e| e.to_lowercase() ).collect();
+
+        Ok(Scope {
+            reference:               options.lookup::<_, Option<String>>( URL )?.map( MutURI::new ),
+            include_subdomains:      options.lookup( INCLUDE_SUBDOMAINS )?,
+            registrable_domains:     options.lookup::<_, Option<bool>>( REGISTRABLE_DOMAINS )?.
+                unwrap_or( true ),
+            https_only:              options.lookup( HTTPS_ONLY )?,
+            depth_limit:             options.lookup( DEPTH_LIMIT )?,
+            directory_depth_limit:   options.lookup( DIRECTORY_DEPTH_LIMIT )?,
+            include_path_patterns:   compile_all( options.lookup( INCLUDE_PATH_PATTERNS )? )?,
+            exclude_path_patterns:   compile_all( options.lookup( EXCLUDE_PATH_PATTERNS )? )?,
+            exclude_file_extensions: exclude_file_extensions,
+            redundant_path_patterns: redundant_path_patterns,
+            redundant_counters:      BoxValue::new( redundant_counters ),
+            auto_redundant_paths:    options.lookup( AUTO_REDUNDANT_PATHS )?,
+            auto_redundant_counter:  BoxValue::new( counters( AUTO_REDUNDANT_COUNTER )? ),
+            auto_redundant_shapes:   options.lookup( AUTO_REDUNDANT_SHAPES )?,
+            shape_counter:           BoxValue::new( counters( AUTO_REDUNDANT_SHAPE_COUNTER )? )
+        })
+    }
+
+    /// `depth` is the crawl depth the URL was found at, if known.
+    fn is_too_deep( &self, url: &MutURI, depth: Option<i64> ) -> bool {
+        if let Some(limit) = self.directory_depth_limit {
+            let slashes = url.path().map_or( 0, |p| p.matches( SLASH ).count() as i64 );
+            if limit > 0 && limit + 1 <= slashes { return true }
+        }
+
+        match (self.depth_limit, depth) {
+            (Some(limit), Some(depth)) => depth >= limit,
+            _                          => false
+        }
+    }
+
+    fn is_exclude_path_patterns( &self, url: &MutURI ) -> bool {
+        if self.exclude_path_patterns.is_empty() { return false }
+
+        let s = url.to_s();
+        self.exclude_path_patterns.iter().any( |pattern| pattern.is_match( &s ) )
+    }
+
+    fn is_exclude_file_extension( &self, url: &MutURI ) -> bool {
+        if self.exclude_file_extensions.is_empty() { return false }
+
+        let extension = url.resource_extension().unwrap_or_default().to_lowercase();
+        self.exclude_file_extensions.contains( &extension )
+    }
+
+    fn is_exclude( &self, url: &MutURI ) -> bool {
+        self.is_exclude_file_extension( url ) || self.is_exclude_path_patterns( url )
+    }
+
+    fn is_include( &self, url: &MutURI ) -> bool {
+        if self.include_path_patterns.is_empty() { return true }
+
+        let s = url.to_s();
+        self.include_path_patterns.iter().any( |pattern| pattern.is_match( &s ) )
+    }
+
+    /// What `url` has to share with the seed URL when subdomains are included.
+    fn site( &self, url: &MutURI ) -> Option<String> {
+        if self.registrable_domains {
+            if let Some(domain) = url.registrable_domain() { return Some(domain) }
+        }
+
+        url.domain()
+    }
+
+    fn is_in_domain( &self, url: &MutURI ) -> bool {
+        if let Some(ref reference) = self.reference {
+            if self.include_subdomains {
+                return self.site( reference ) == self.site( url )
+            }
+
+            return reference.host() == url.host()
+        }
+
+        true
+    }
+
+    fn is_follow_protocol( &self, url: &MutURI ) -> bool {
+        if let Some(ref reference) = self.reference {
+            let check_scheme = match url.scheme() {
+                Some(scheme) => scheme,
+                None         => return false
+            };
+
+            let ref_scheme = reference.scheme();
+            if ref_scheme.as_ref().map( |s| s.as_str() ) != Some(HTTPS) { return true }
+            if ref_scheme == Some(check_scheme.clone()) { return true }
+
+            // Not a downgrade, like `wss` WebSocket endpoints on HTTPS pages.
+            if is_secure( &check_scheme ) { return true }
+
+            return !self.https_only
+        }
+
+        true
+    }
+
+    fn is_auto_redundant( &self, url: &MutURI, update_counters: bool ) -> Result<bool, Error> {
+        let limit = match self.auto_redundant_paths {
+            Some(limit) => limit,
+            None        => return Ok(false)
+        };
+
+        let params = url.query_parameters();
+        if params.is_empty() { return Ok(false) }
+
+        reached( *self.auto_redundant_counter, auto_redundant_key( url, &params )?, limit, update_counters )
+    }
+
+    /// Like `is_auto_redundant` but keyed on `URIExt#shape_hash`, so it also catches paths
+    /// that only differ in their IDs.
+    fn is_shape_redundant( &self, url: &MutURI, update_counters: bool ) -> Result<bool, Error> {
+        let limit = match self.auto_redundant_shapes {
+            Some(limit) => limit,
+            None        => return Ok(false)
+        };
+
+        reached( *self.shape_counter, url.shape_hash(), limit, update_counters )
+    }
+
+    fn is_redundant( &self, url: &MutURI, update_counters: bool ) -> Result<bool, Error> {
+        if self.is_auto_redundant( url, update_counters )? { return Ok(true) }
+        if self.is_shape_redundant( url, update_counters )? { return Ok(true) }
+
+        let s        = url.to_s();
+        let counters = *self.redundant_counters;
+
+        for &(ref pattern, ref key) in &self.redundant_path_patterns {
+            if !pattern.is_match( &s ) { continue }
+
+            let count: i64 = counters.aref( **key )?;
+            if count == 0 { return Ok(true) }
+
+            if !update_counters { continue }
+            counters.aset( **key, count - 1 )?;
+        }
+
+        Ok(false)
+    }
+
+    /// Does **not** check for redundancy.
+    fn is_out( &self, url: &MutURI, depth: Option<i64> ) -> bool {
+        !self.is_follow_protocol( url ) || !self.is_in_domain( url ) ||
+            self.is_too_deep( url, depth ) || !self.is_include( url ) || self.is_exclude( url )
+    }
+
+    fn is_in( &self, url: &MutURI, depth: Option<i64> ) -> bool {
+        !self.is_out( url, depth )
+    }
+}
+
+pub fn initialize() -> Result<(), Error> {
+    let scnr_ns = class::object().const_get::<_, RModule>("SCNR")?;
+    let engine_ns = scnr_ns.const_get::<_, RModule>("Engine")?;
+    let rust_ns = engine_ns.define_module("Rust")?;
+    let uri_ns = rust_ns.define_module("URI")?;
+    let scope_class = uri_ns.define_class("Scope", class::object())?;
+
+    scope_class.define_singleton_method("new", function!(Scope::new, 1))?;
+
+    scope_class.define_method("too_deep?", method!(Scope::is_too_deep, 2))?;
+    scope_class.define_method("exclude?", method!(Scope::is_exclude, 1))?;
+    scope_class.define_method("exclude_path_patterns?", method!(Scope::is_exclude_path_patterns, 1))?;
+    scope_class.define_method("exclude_file_extension?", method!(Scope::is_exclude_file_extension, 1))?;
+    scope_class.define_method("include?", method!(Scope::is_include, 1))?;
+    scope_class.define_method("in_domain?", method!(Scope::is_in_domain, 1))?;
+    scope_class.define_method("follow_protocol?", method!(Scope::is_follow_protocol, 1))?;
+    scope_class.define_method("auto_redundant?", method!(Scope::is_auto_redundant, 2))?;
+    scope_class.define_method("shape_redundant?", method!(Scope::is_shape_redundant, 2))?;
+    scope_class.define_method("redundant?", method!(Scope::is_redundant, 2))?;
+    scope_class.define_method("in?", method!(Scope::is_in, 2))?;
+    scope_class.define_method("out?", method!(Scope::is_out, 2))?;
+
+    Ok(())
+}
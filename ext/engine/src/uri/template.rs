@@ -0,0 +1,336 @@
+//! Infers `Element::LinkTemplate` templates from discovered URLs.
+//!
+//! URLs with the same origin and number of path segments are clustered together, segments
+//! that vary across a cluster become inputs, like `/user/{int}/profile`.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+use magnus::{class, function, method, Error, RArray, RHash, RModule, RRegexp, Value, prelude::*};
+use magnus::typed_data::Obj;
+
+use uri::{MutURI, segment_shape, INT_SHAPE, UUID_SHAPE, HEX_SHAPE, SLASH};
+
+/// Placeholder for segments that vary but have no recognizable shape.
+const STR_SHAPE: &str = "{str}";
+
+/// Distinct values a shapeless segment needs before it's considered an input rather than a
+/// handful of separate resources, like `/user/1/profile` and `/user/1/settings`.
+const STR_MIN_DISTINCT: usize = 5;
+
+/// A template needs at least this many distinct paths to be reported.
+const MIN_SAMPLES: usize = 2;
+
+/// Distinct paths kept per cluster, further ones are ignored.
+const MAX_SAMPLES: usize = 1_000;
+
+const DEFAULT_INPUT_NAME: &str = "input";
+
+// `templates` keys.
+const TEMPLATE:   &str = "template";
+const PATTERN:    &str = "pattern";
+const CONFIDENCE: &str = "confidence";
+const SAMPLES:    &str = "samples";
+
+/// Clusters on origin, segment count and trailing slash.
+type ClusterKey = (String, usize, bool);
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Part {
+    Literal( String ),
+    Input( &'static str )
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Template {
+    origin:         String,
+    parts:          Vec<Part>,
+    trailing_slash: bool,
+    samples:        usize
+}
+
+impl Template {
+
+    /// Like `http://test.com/user/{int}/profile`.
+    pub fn to_template( &self ) -> String {
+        let mut template = self.origin.clone();
+
+        for part in &self.parts {
+            template.push( SLASH );
+
+            match *part {
+                Part::Literal( ref segment ) => template.push_str( segment ),
+                Part::Input( shape )         => template.push_str( shape )
+            }
+        }
+
+        if self.trailing_slash { template.push( SLASH ) }
+        template
+    }
+
+    /// Regular expression, in Ruby syntax, with a named capture per input, named after the
+    /// preceding segment when possible, as expected by `Element::LinkTemplate`.
+    pub fn to_pattern( &self ) -> Result<String, Error> {
+        let mut pattern = String::from( "\\A" );
+        pattern.push_str( &escape( &self.origin )? );
+
+        let mut names = HashSet::new();
+
+        for (i, part) in self.parts.iter().enumerate() {
+            pattern.push( SLASH );
+
+            let shape = match *part {
+                Part::Literal( ref segment ) => {
+                    pattern.push_str( &escape( segment )? );
+                    continue
+                },
+                Part::Input( shape ) => shape
+            };
+
+            let name = self.input_name( i, &mut names );
+            pattern.push_str( &format!( "(?<{}>{})", name, shape_pattern( shape ) ) );
+        }
+
+        if self.trailing_slash { pattern.push( SLASH ) }
+
+        // Anything after the path is fine.
+        pattern.push_str( "(?=[;?#]|\\z)" );
+        Ok(pattern)
+    }
+
+    /// Confidence grows with the number of distinct paths seen, 2 paths give `0.75`.
+    pub fn confidence( &self ) -> f64 {
+        1.0 - 0.5_f64.powi( self.samples as i32 )
+    }
+
+    fn input_name( &self, i: usize, taken: &mut HashSet<String> ) -> String {
+        let base = match i.checked_sub( 1 ).map( |p| &self.parts[p] ) {
+            Some(&Part::Literal( ref segment )) => sanitize( segment ),
+            _                                   => DEFAULT_INPUT_NAME.to_string()
+        };
+
+        let mut name = base.clone();
+        let mut n    = 2;
+
+        while taken.contains( &name ) {
+            name = format!( "{}_{}", base, n );
+            n += 1;
+        }
+
+        taken.insert( name.clone() );
+        name
+    }
+}
+
+/// `Regexp.escape`, the pattern ends up as a Ruby `Regexp`.
+fn escape( literal: &str ) -> Result<String, Error> {
+    class::regexp().funcall( "escape", (literal,) )
+}
+
+/// Ruby capture group names need to be word characters, not starting with a digit.
+fn sanitize( segment: &str ) -> String {
+    let name: String = segment.chars().
+        map( |c| if c.is_ascii_alphanumeric() { c } else { '_' } ).
+        collect();
+
+    let name = name.trim_matches( '_' );
+
+    if name.is_empty() { return DEFAULT_INPUT_NAME.to_string() }
+    if name.starts_with( |c: char| c.is_ascii_digit() ) {
+        return format!( "{}_{}", DEFAULT_INPUT_NAME, name )
+    }
+
+    name.to_string()
+}
+
+fn shape_pattern( shape: &str ) -> &'static str {
+    match shape {
+        INT_SHAPE  => "\\d+",
+        UUID_SHAPE => "[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}",
+        HEX_SHAPE  => "[0-9a-fA-F]+",
+        _          => "[^/;?#]+"
+    }
+}
+
+fn to_parts( segments: &[String] ) -> Vec<Part> {
+    segments.iter().map( |segment|
+        match segment_shape( segment ) {
+            Some(shape) => Part::Input( shape ),
+            None        => Part::Literal( segment.clone() )
+        }
+    ).collect()
+}
+
+/// Turns literals at position `i` into a `STR_SHAPE` input wherever enough of them only differ
+/// at that position.
+fn merge_literals( counts: HashMap<Vec<Part>, usize>, i: usize ) -> HashMap<Vec<Part>, usize> {
+    let mut siblings: HashMap<Vec<Part>, Vec<(Vec<Part>, usize)>> = HashMap::new();
+
+    for (parts, samples) in counts {
+        let mut masked = parts.clone();
+        if let Part::Literal(..) = parts[i] {
+            masked[i] = Part::Input( STR_SHAPE );
+        }
+
+        siblings.entry( masked ).or_insert_with( Vec::new ).push( (parts, samples) );
+    }
+
+    let mut merged = HashMap::new();
+    for (masked, group) in siblings {
+        if group.len() >= STR_MIN_DISTINCT {
+            *merged.entry( masked ).or_insert( 0 ) += group.iter().map( |&(_, samples)| samples ).sum::<usize>();
+            continue
+        }
+
+        for (parts, samples) in group {
+            *merged.entry( parts ).or_insert( 0 ) += samples;
+        }
+    }
+
+    merged
+}
+
+/// Templates found in the URLs of a cluster.
+///
+/// ID-like segments are always inputs, other segments only become inputs when they're the
+/// only difference between enough paths.
+fn cluster_templates( key: &ClusterKey, samples: &HashSet<Vec<String>> ) -> Vec<Template> {
+    let &(ref origin, length, trailing_slash) = key;
+
+    let mut counts: HashMap<Vec<Part>, usize> = HashMap::new();
+    for sample in samples {
+        *counts.entry( to_parts( sample ) ).or_insert( 0 ) += 1;
+    }
+
+    for i in 0..length {
+        counts = merge_literals( counts, i );
+    }
+
+    counts.into_iter().
+        filter( |&(ref parts, samples)|
+            samples >= MIN_SAMPLES && parts.iter().any( |p| if let Part::Input(..) = *p { true } else { false } )
+        ).
+        map( |(parts, samples)| Template {
+            origin:         origin.clone(),
+            parts:          parts,
+            trailing_slash: trailing_slash,
+            samples:        samples
+        }).
+        collect()
+}
+
+#[magnus::wrap(class = "SCNR::Engine::Rust::URI::TemplateInferencer", free_immediately, size)]
+pub struct TemplateInferencer {
+    clusters: RefCell<HashMap<ClusterKey, HashSet<Vec<String>>>>
+}
+
+impl TemplateInferencer {
+
+    fn new() -> Self {
+        TemplateInferencer {
+            clusters: RefCell::new( HashMap::new() )
+        }
+    }
+
+    /// Adds `url` to the samples, URLs without a path are ignored.
+    pub fn push( &self, url: &MutURI ) {
+        let path = match url.path() {
+            Some(path) => path,
+            None       => return
+        };
+
+        let trimmed = path.trim_start_matches( SLASH );
+        if trimmed.is_empty() { return }
+
+        let trailing_slash = trimmed.ends_with( SLASH );
+        let segments: Vec<String> = trimmed.trim_end_matches( SLASH ).
+            split( SLASH ).map( |s| s.to_string() ).collect();
+
+        let key = (url.up_to_port(), segments.len(), trailing_slash);
+
+        let mut clusters = self.clusters.borrow_mut();
+        let samples      = clusters.entry( key ).or_insert_with( HashSet::new );
+
+        if samples.len() >= MAX_SAMPLES { return }
+        samples.insert( segments );
+    }
+
+    /// Adds the `URIExt` objects in `urls`.
+    fn push_all( &self, urls: RArray ) -> Result<(), Error> {
+        // Copy the elements out before anything else can touch the array.
+        let urls: Vec<Value> = unsafe { urls.as_slice() }.to_vec();
+
+        for url in urls {
+            let url: Obj<MutURI> = Obj::try_convert( url )?;
+            self.push( &url );
+        }
+
+        Ok(())
+    }
+
+    /// Inferred templates, most confident first.
+    pub fn templates_by_confidence( &self ) -> Vec<Template> {
+        let mut templates: Vec<Template> = self.clusters.borrow().iter().
+            flat_map( |(key, samples)| cluster_templates( key, samples ) ).
+            collect();
+
+        templates.sort_by( |a, b|
+            b.samples.cmp( &a.samples ).then_with( || a.to_template().cmp( &b.to_template() ) )
+        );
+
+        templates
+    }
+
+    fn templates( &self ) -> Result<RArray, Error> {
+        let templates = RArray::new();
+
+        for template in self.templates_by_confidence() {
+            let hash = RHash::new();
+            hash.aset( TEMPLATE, template.to_template() )?;
+            hash.aset( PATTERN, RRegexp::new( &template.to_pattern()?, Default::default() )? )?;
+            hash.aset( CONFIDENCE, template.confidence() )?;
+            hash.aset( SAMPLES, template.samples )?;
+
+            templates.push( hash )?;
+        }
+
+        Ok(templates)
+    }
+
+    /// Patterns of the templates that are at least `min_confidence` confident, ready for
+    /// `OptionGroups::Audit#link_templates`.
+    fn patterns( &self, min_confidence: f64 ) -> Result<Vec<RRegexp>, Error> {
+        self.templates_by_confidence().iter().
+            filter( |t| t.confidence() >= min_confidence ).
+            map( |t| RRegexp::new( &t.to_pattern()?, Default::default() ) ).
+            collect()
+    }
+
+    fn size( &self ) -> usize {
+        self.clusters.borrow().values().map( |samples| samples.len() ).sum()
+    }
+
+    fn clear( &self ) {
+        self.clusters.borrow_mut().clear()
+    }
+}
+
+pub fn initialize() -> Result<(), Error> {
+    let scnr_ns = class::object().const_get::<_, RModule>("SCNR")?;
+    let engine_ns = scnr_ns.const_get::<_, RModule>("Engine")?;
+    let rust_ns = engine_ns.define_module("Rust")?;
+    let uri_ns = rust_ns.define_module("URI")?;
+    let inferencer_class = uri_ns.define_class("TemplateInferencer", class::object())?;
+
+    inferencer_class.define_singleton_method("new", function!(TemplateInferencer::new, 0))?;
+
+    inferencer_class.define_method("<<", method!(TemplateInferencer::push, 1))?;
+    inferencer_class.define_method("push", method!(TemplateInferencer::push, 1))?;
+    inferencer_class.define_method("push_all", method!(TemplateInferencer::push_all, 1))?;
+    inferencer_class.define_method("templates", method!(TemplateInferencer::templates, 0))?;
+    inferencer_class.define_method("patterns", method!(TemplateInferencer::patterns, 1))?;
+    inferencer_class.define_method("size", method!(TemplateInferencer::size, 0))?;
+    inferencer_class.define_method("clear", method!(TemplateInferencer::clear, 0))?;
+
+    Ok(())
+}
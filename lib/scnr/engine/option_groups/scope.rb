@@ -130,6 +130,17 @@ class Scope < SCNR::Engine::OptionGroup
     # @see URICommon::Scope#auto_redundant?
     attr_accessor :auto_redundant_paths
 
+    # @return   [Integer]
+    #   Sets a limit to how many URLs with the same shape to process, i.e. with
+    #   the same parameter names and only differing in ID-like path segments.
+    #   Catches pagination and calendar links that {#auto_redundant_paths}
+    #   misses.
+    #
+    # @see URICommon::Scope#redundant?
+    # @see URICommon::Scope#shape_redundant?
+    # @see URIExt#shape_hash
+    attr_accessor :auto_redundant_shapes
+
     # @return    [Array<Regexp>]
     #   Path inclusion patterns, only resources that match any of the specified
     #   patterns will be considered.
@@ -176,6 +187,18 @@ class Scope < SCNR::Engine::OptionGroup
     # @see URICommon::Scope#in_domain?
     attr_accessor :include_subdomains
 
+    # @note Default is `true`.
+    #
+    # @return    [Bool]
+    #   Tell subdomains apart by their registrable domain, based on the Public
+    #   Suffix List, so that `a.example.co.uk` and `b.example.co.uk` share
+    #   `example.co.uk` rather than `co.uk`.
+    #
+    # @see URICommon::Scope#in_domain?
+    # @see URIExt#registrable_domain
+    attr_accessor :registrable_domains
+    alias :registrable_domains? :registrable_domains
+
     # @return   [Bool]
     #   If an HTTPS {Options#url} has been provided, **do not** downgrade to to
     #   a insecure link.
@@ -196,6 +219,7 @@ class Scope < SCNR::Engine::OptionGroup
         depth_limit:                 10,
         directory_depth_limit:       10,
         auto_redundant_paths:        15,
+        registrable_domains:         true,
         redundant_path_patterns:     {},
         dom_depth_limit:             4,
         dom_event_limit:             500,
@@ -261,6 +285,14 @@ class Scope < SCNR::Engine::OptionGroup
         @auto_redundant_counter ||= Hash.new( 0 )
     end
 
+    def auto_redundant_shapes?
+        !!@auto_redundant_shapes
+    end
+
+    def auto_redundant_shape_counter
+        @auto_redundant_shape_counter ||= Hash.new( 0 )
+    end
+
     def do_not_crawl
         self.page_limit = 0
     end
@@ -311,6 +343,18 @@ class Scope < SCNR::Engine::OptionGroup
              end
     end
 
+    # @return   [Hash]
+    #   Like {#to_rpc_data} but with the patterns left as `Regexp`s, flags and
+    #   all, for `Rust::URI::Scope`, along with the live redundancy counters so
+    #   that it and {URICommon::Scope} count the same URLs.
+    def to_native_data
+        d = to_h.my_stringify_keys( false )
+        d['exclude_file_extensions']      = d['exclude_file_extensions'].to_a
+        d['auto_redundant_counter']       = auto_redundant_counter
+        d['auto_redundant_shape_counter'] = auto_redundant_shape_counter
+        d
+    end
+
     def to_rpc_data
         d = super
 
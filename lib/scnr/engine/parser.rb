@@ -41,6 +41,14 @@ class Parser
         'Content-Length'
     ]
 
+    # Path extractors whose work is done by the native `Nodes::Base#paths`,
+    # when the document has it.
+    #
+    # `data_url` isn't one of them, it scans the raw body, so it also catches
+    # `data-url` attributes in markup that scripts write.
+    NATIVE_EXTRACTORS = %w(anchors areas base comments forms frames links
+                            meta_refresh scripts)
+
     class SAX
         class Stop < RuntimeError
         end
@@ -50,7 +58,12 @@ class Parser
 
         def parse( html, options = {} )
             CACHE[__method__].fetch [html, options] do
-                Document.parse( html.delete( "\0" ), !!options[:filter] )
+                Document.parse(
+                    html.delete( "\0" ),
+                    options[:filter] || false,
+                    !!options[:spec_compliant],
+                    !!options[:positions]
+                )
             end
         end
 
@@ -80,6 +93,21 @@ class Parser
             [buffer_in, document]
         end
 
+        # Parses HTML as it arrives, keeping the document as parsed so far.
+        #
+        # Requires the extension.
+        #
+        # @param    [Hash]  options
+        # @option   options [Bool, Symbol, String, Hash]  :filter
+        #   See {Ext::Document.filter_profile}.
+        # @option   options [Bool]  :positions
+        #   See {Ext::Document.parse}.
+        #
+        # @return   [Ext::PushParser]
+        def push_parser( options = {} )
+            Document.push_parser( options[:filter] || false, !!options[:positions] )
+        end
+
         def parse_fragment( html )
             parse( html ).traverse { |n| return n }
         end
@@ -471,7 +499,24 @@ class Parser
             downcased_body = self.body&.downcase
 
             unsanitized_paths = Set.new
-            self.class.extractors.available.each do |name|
+            available         = self.class.extractors.available
+
+            # The native pass does the work of the stock extractors in one go,
+            # as long as they haven't been removed, and covers a few more
+            # attributes besides.
+            if document.respond_to?( :paths )
+                document.paths.each do |path, extractor|
+                    extractor = extractor.to_s
+                    next if NATIVE_EXTRACTORS.include?( extractor ) &&
+                        !available.include?( extractor )
+
+                    unsanitized_paths << path
+                end
+
+                available -= NATIVE_EXTRACTORS
+            end
+
+            available.each do |name|
                 exception_jail false do
                     unsanitized_paths.merge self.class.extractors[name].new(
                         parser:         self,
@@ -9,6 +9,7 @@
 require_relative 'nodes/comment'
 require_relative 'nodes/text'
 require_relative 'nodes/element'
+require_relative 'push_parser'
 
 module SCNR::Engine
 class Parser
@@ -20,8 +21,70 @@ class Document < Nodes::Base
         :document
     end
 
-    def self.parse( html, filter = false )
-        new SCNR::Engine::Rust::Parser::Node.parse( html, filter )
+    # @param    [String]    html
+    # @param    [Bool, Symbol, String, Hash, Rust::Parser::FilterProfile]  filter
+    #   Only keep what's of interest, see {.filter_profile}.
+    # @param    [Bool]  spec_compliant
+    #   Build the tree the way the HTML spec says, with implied end tags,
+    #   misnested elements fixed up and foster parenting, so that it matches
+    #   what the {Browser} sees; slower than the default SAX-style parse.
+    # @param    [Bool]  positions
+    #   Track where nodes came from in the source, for {Nodes::Base#position}
+    #   and {Nodes::Base#source_range}; off by default as it slows parsing down.
+    #
+    # @return   [Document]
+    def self.parse( html, filter = false, spec_compliant = false, positions = false )
+        new SCNR::Engine::Rust::Parser::Node.parse(
+            html, filter_profile( filter ), !!spec_compliant, !!positions
+        )
+    end
+
+    # @return   [PushParser]
+    def self.push_parser( filter = false, positions = false )
+        PushParser.new( filter, positions )
+    end
+
+    # @param    [Bool, Symbol, String, Hash, Rust::Parser::FilterProfile]  filter
+    #
+    #   * `true` -- The `:default` profile: forms, inputs, links and
+    #       set-cookie/refresh meta tags.
+    #   * `Symbol`, `String` -- A named profile: `:default`, `:paths` or
+    #       `:json_ld`.
+    #   * `Hash` -- A custom profile:
+    #       * `:elements` -- Element rules, like `script[src]` or
+    #           `script[type=application/ld+json]`, rules for the same
+    #           element are alternatives.
+    #       * `:text` -- Elements whose text is kept.
+    #       * `:others` -- Whether to keep elements without a rule.
+    #
+    #   Custom profiles are compiled each time, keep the result around to
+    #   parse many documents with the same one.
+    #
+    # @return   [Rust::Parser::FilterProfile, nil]
+    #
+    # @raise    [ArgumentError]
+    #   On unknown profile names and invalid rules.
+    def self.filter_profile( filter )
+        case filter
+            when nil, false
+                nil
+
+            when true
+                filter_profile( :default )
+
+            when Symbol, String
+                SCNR::Engine::Rust::Parser::FilterProfile.named( filter.to_s )
+
+            when Hash
+                SCNR::Engine::Rust::Parser::FilterProfile.new(
+                    Array( filter[:elements] ).map( &:to_s ),
+                    Array( filter[:text] ).map( &:to_s ),
+                    !!filter[:others]
+                )
+
+            else
+                filter
+        end
     end
 
 end
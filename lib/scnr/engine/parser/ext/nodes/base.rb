@@ -74,6 +74,84 @@ class Base
         nil
     end
 
+    # @param   [String]    selector
+    #   CSS selector.
+    #
+    # @return   [Array<Nodes::Base>]
+    #   Descendant nodes that match `selector`, in document order.
+    #
+    # @raise    [ArgumentError]
+    #   On an invalid `selector`.
+    def css( selector )
+        @native.css( selector.to_s ).map { |native| Nodes::Base.from_native( native ) }
+    end
+
+    # @param   [String]    selector
+    #   CSS selector.
+    #
+    # @return   [Nodes::Base, nil]
+    #   First descendant node that matches `selector`.
+    #
+    # @raise    [ArgumentError]
+    #   On an invalid `selector`.
+    def at_css( selector )
+        native = @native.at_css( selector.to_s )
+        native && Nodes::Base.from_native( native )
+    end
+
+    # @param   [String]    expression
+    #   XPath 1.0 expression, location paths with predicates, `text()`,
+    #   `contains()` and the like; no namespaces, variables or arithmetic.
+    #
+    # @return   [Array<Nodes::Base, String>]
+    #   Matching nodes in document order, attributes as their values.
+    #
+    # @raise    [ArgumentError]
+    #   On an invalid `expression` or one that doesn't select nodes.
+    def xpath( expression )
+        @native.xpath( expression.to_s ).map do |native|
+            native.is_a?( String ) ? native : Nodes::Base.from_native( native )
+        end
+    end
+
+    # @return   [Hash{Symbol => Integer}, nil]
+    #   Where the node starts in the source, as a byte `:offset` along with a
+    #   1-based `:line` and `:column`; `nil` for the document and unless
+    #   parsed with `positions`.
+    def position
+        @native.position
+    end
+
+    # @return   [Hash{Symbol => Hash}, nil]
+    #   `:start` and `:end` {#position positions} of the node's markup, from
+    #   its start tag to its end tag for elements; `:end` is exclusive.
+    def source_range
+        @native.source_range
+    end
+
+    # @return   [Array<Hash{Symbol => Object}>]
+    #   Descendant forms in document order, with their `:action` (`nil` if
+    #   missing), `:method`, `:enctype`, `:id`, `:name`, `:attributes` and
+    #   `:fields`.
+    #
+    #   Fields carry their `:tag`, `:name`, `:id`, `:type`, default `:value`,
+    #   `:checked` state, `:attributes` and, for selects, `:options` with their
+    #   `:value`, `:text` and `:selected` state; fields outside of a form are
+    #   included when their `form` attribute points to it.
+    def forms
+        @native.forms
+    end
+
+    # @return   [Array<Array(String, Symbol)>]
+    #   Paths found in the node and its descendants, in document order, each
+    #   paired with the `path_extractors` component that would find it --
+    #   or with the attribute it came from (`:srcset`, `:formaction`,
+    #   `:poster`, `:data`, `:ping`, `:manifest`) when there's no such
+    #   component; paths are left unresolved.
+    def paths
+        @native.paths
+    end
+
     def hash
         to_s.hash
     end
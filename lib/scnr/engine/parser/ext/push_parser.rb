@@ -0,0 +1,66 @@
+=begin
+    Copyright 2024 Ecsypno Single Member P.C.
+
+    This file is part of the SCNR::Engine project and is subject to
+    redistribution and commercial restrictions. Please see the SCNR::Engine
+    web site for more information on licensing and terms of use.
+=end
+
+module SCNR::Engine
+class Parser
+module Ext
+
+# Parses HTML as it arrives, one chunk at a time.
+#
+# The {#document} can be inspected at any point, so there's no need to wait
+# for the rest of the response once it has what we're after.
+#
+# @author Tasos "Zapotek" Laskos <tasos.laskos@gmail.com>
+class PushParser
+
+    # @param    [Bool, Symbol, String, Hash, Rust::Parser::FilterProfile]  filter
+    #   See {Document.filter_profile}.
+    # @param    [Bool]  positions
+    #   See {Document.parse}.
+    def initialize( filter = false, positions = false )
+        @native = SCNR::Engine::Rust::Parser::PushParser.new(
+            Document.filter_profile( filter ), !!positions
+        )
+    end
+
+    # @param    [String]    chunk
+    #   Next piece of the body, it can end in the middle of a character.
+    #
+    # @return   [PushParser]
+    #   `self`
+    #
+    # @raise    [RuntimeError]
+    #   If the parser has already {#finish finished}.
+    def <<( chunk )
+        @native << chunk.delete( "\0" )
+        self
+    end
+
+    # @return   [Document]
+    #   Document as parsed so far.
+    def document
+        Document.new @native.document
+    end
+
+    # Parses whatever is left, no more chunks can be pushed after that.
+    #
+    # @return   [Document]
+    def finish
+        Document.new @native.finish
+    end
+
+    # @return   [Bool]
+    def finished?
+        @native.finished?
+    end
+
+end
+
+end
+end
+end
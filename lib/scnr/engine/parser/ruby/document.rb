@@ -34,7 +34,8 @@ class Document < Nodes::Base
         html << "\n"
     end
 
-    def self.parse( html, filter = false )
+    # `spec_compliant` is ignored, Ox has no tree builder.
+    def self.parse( html, filter = false, spec_compliant = false )
         options = {}
         if filter
             options[:whitelist] = WHITELIST
@@ -156,6 +156,9 @@ module URICommon
         # Uses {.parse} to parse and normalize the URL and then converts it to
         # a common {String} format.
         #
+        # Dot segments are kept where the parser supports it, request and element
+        # URLs go through here and path traversal payloads need their raw form.
+        #
         # @param    [String]    url
         #
         # @return   [String]
@@ -172,7 +175,10 @@ module URICommon
                     return v
                 end
 
-                cache[url] = parse( url ).to_s
+                parsed = respond_to?( :with_dot_segments ) ?
+                    with_dot_segments( url ) : parse( url )
+
+                cache[url] = parsed.to_s
             rescue => e
                 print_debug "Failed to normalize '#{url}'."
                 print_debug "Error: #{e}"
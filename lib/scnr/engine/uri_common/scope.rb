@@ -107,10 +107,25 @@ class Scope < SCNR::Engine::Scope
         return true if !Options.url
 
         options.include_subdomains ?
-            Options.parsed_url.domain == @url.domain :
+            site( Options.parsed_url ) == site( @url ) :
             Options.parsed_url.host == @url.host
     end
 
+    # @param    [SCNR::Engine::URI]  url
+    #
+    # @return   [String]
+    #   What `url` has to share with {Options#url} when subdomains are included,
+    #   its registrable domain if {OptionGroups::Scope#registrable_domains} and
+    #   supported, its {URIExt#domain domain} otherwise.
+    def site( url )
+        if options.registrable_domains? && url.respond_to?( :registrable_domain )
+            domain = url.registrable_domain
+            return domain if domain
+        end
+
+        url.domain
+    end
+
     # @return   [Bool]
     #   `true` if the protocol is within scope based on
     #   {OptionGroups::Scope#https_only}, `false` otherwise.
@@ -127,6 +142,7 @@ class Scope < SCNR::Engine::Scope
 
         return true if ref_scheme != 'https'
         return true if ref_scheme == check_scheme
+        return true if check_scheme == 'wss'
 
         !options.https_only?
     end
@@ -143,6 +159,7 @@ class Scope < SCNR::Engine::Scope
     # @see OptionGroups::Scope#redundant_path_patterns
     def redundant?( update_counters = false )
         return true if auto_redundant?( update_counters )
+        return true if shape_redundant?( update_counters )
         url_string = @url.to_s
 
         options.redundant_path_patterns.each do |regexp, count|
@@ -183,6 +200,33 @@ class Scope < SCNR::Engine::Scope
         false
     end
 
+    # @note Only applies to URLs that support {URIExt#shape_hash}.
+    #
+    # @param    [Bool]   update_counters
+    #   Whether or not to increment the counters if `self` is redundant.
+    #
+    # @return   [Bool]
+    #   `true` if the URL is redundant based on {OptionGroups::Scope#auto_redundant_shapes},
+    #   `false` otherwise.
+    #
+    # @see OptionGroups::Scope#auto_redundant_shapes
+    def shape_redundant?( update_counters = false )
+        return false if !options.auto_redundant_shapes?
+        return false if !@url.respond_to?( :shape_hash )
+
+        h = @url.shape_hash
+
+        if options.auto_redundant_shape_counter[h] >= options.auto_redundant_shapes
+            return true
+        end
+
+        if update_counters
+            options.auto_redundant_shape_counter[h] += 1
+        end
+
+        false
+    end
+
     # @return   [Bool]
     #   `true` if the URL is not {#out?} of the scan {OptionGroups::Scope scope},
     #   `false` otherwise.
@@ -25,6 +25,21 @@ module SCNR::Engine
 class URIExt
     include URICommon
 
+    # {URIExt} error namespace.
+    #
+    # All {URIExt} errors inherit from and live under it.
+    #
+    # @author Tasos "Zapotek" Laskos <tasos.laskos@gmail.com>
+    class Error < SCNR::Engine::Error
+
+        # Raised by {.strict} when a URL can't be parsed, the message includes
+        # the reason.
+        #
+        # @author Tasos "Zapotek" Laskos <tasos.laskos@gmail.com>
+        class InvalidURL < Error
+        end
+    end
+
     class <<self
 
         # @private
@@ -32,6 +47,25 @@ class URIExt
             _decode_ext( string ).force_encoding( 'utf-8' )
         end
 
+        alias :to_absolute_all_ext :to_absolute_all
+
+        # Batch, uncached version of {URICommon::ClassMethods.to_absolute}, for when there are
+        # many links to resolve against the same reference.
+        #
+        # @param    [Array<String>]   hrefs
+        # @param    [URIExt, String]  reference
+        #   Absolute URL to use as a reference.
+        #
+        # @return   [Array<String, nil>]
+        #   Absolute URLs, in the order of `hrefs`, with `nil` for the invalid ones.
+        def to_absolute_all( hrefs, reference = Options.instance.url.to_s )
+            if !reference.is_a?( self )
+                reference = new( reference.to_s )
+            end
+
+            to_absolute_all_ext( hrefs, reference )
+        end
+
     end
 
     alias :to_absolute_ext! :to_absolute!
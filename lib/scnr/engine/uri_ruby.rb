@@ -28,7 +28,13 @@ class URIRuby
 
     QUERY_CHARACTER_CLASS = Addressable::URI::CharacterClasses::QUERY.sub( '\\&', '' )
 
-    VALID_SCHEMES     = Set.new(%w(http https))
+    DEFAULT_PORTS     = {
+        'http'  => 80,
+        'https' => 443,
+        'ws'    => 80,
+        'wss'   => 443
+    }
+    VALID_SCHEMES     = Set.new(DEFAULT_PORTS.keys)
     PARTS             = %w(scheme userinfo host port path query)
     TO_ABSOLUTE_PARTS = %w(scheme userinfo host port)
 
@@ -117,7 +123,12 @@ class URIRuby
                                     components[:port] = splits.last.to_i
                                 end
 
-                                if components[:port] == 80
+                                # Without a scheme 80 is the default, like it
+                                # always was.
+                                default_port = components[:scheme] ?
+                                    DEFAULT_PORTS[components[:scheme]] : 80
+
+                                if components[:port] == default_port
                                     components[:port] = nil
                                 end
                             else
@@ -344,10 +355,7 @@ class URIRuby
     def up_to_port
         uri_str = "#{scheme}://#{host}"
 
-        if port && (
-            (scheme == 'http' && port != 80) ||
-                (scheme == 'https' && port != 443)
-        )
+        if port && port != DEFAULT_PORTS[scheme]
             uri_str << ':'
             uri_str << port.to_s
         end
@@ -481,9 +489,7 @@ class URIRuby
             s << @host
 
             if @port
-                if (@scheme == 'http' && @port != 80) ||
-                    (@scheme == 'https' && @port != 443)
-
+                if @port != DEFAULT_PORTS[@scheme]
                     s << ':'
                     s << @port.to_s
                 end
@@ -5,8 +5,8 @@ describe SCNR::Engine::OptionGroups::Scope do
     subject { described_class.new }
 
     %w(directory_depth_limit dom_depth_limit page_limit restrict_paths extend_paths
-        redundant_path_patterns auto_redundant_paths include_path_patterns
-        exclude_path_patterns exclude_content_patterns include_subdomains https_only
+        redundant_path_patterns auto_redundant_paths auto_redundant_shapes include_path_patterns
+        exclude_path_patterns exclude_content_patterns include_subdomains registrable_domains https_only
         url_rewrites exclude_binaries exclude_file_extensions dom_event_limit
     ).each do |method|
         it { is_expected.to respond_to method }
@@ -59,6 +59,20 @@ describe SCNR::Engine::OptionGroups::Scope do
         end
     end
 
+    describe '#auto_redundant_shapes?' do
+        describe 'when #auto_redundant_shapes has been enabled' do
+            it 'returns true' do
+                subject.auto_redundant_shapes = 10
+                expect(subject.auto_redundant_shapes?).to be_truthy
+            end
+        end
+        describe 'by default' do
+            it 'returns false' do
+                expect(subject.auto_redundant_shapes?).to be_falsey
+            end
+        end
+    end
+
     describe '#redundant_path_patterns=' do
         it 'converts its param to properly typed filters' do
             subject.redundant_path_patterns = { /pattern/ => '45', 'regexp' => 39 }
@@ -269,4 +283,22 @@ describe SCNR::Engine::OptionGroups::Scope do
         end
     end
 
+    describe '#to_native_data' do
+        let(:data) { subject.to_native_data }
+
+        it 'keeps the patterns as Regexp' do
+            subject.exclude_path_patterns = [/Logout/]
+            subject.redundant_path_patterns = { /calendar/ => 1 }
+
+            expect(data['exclude_path_patterns']).to eq([/Logout/])
+            expect(data['redundant_path_patterns']).to eq({ /calendar/ => 1 })
+        end
+
+        it "converts 'exclude_file_extensions' to Array of string" do
+            subject.exclude_file_extensions = Set.new( ['stuff'] )
+
+            expect(data['exclude_file_extensions']).to eq(['stuff'])
+        end
+    end
+
 end
@@ -121,6 +121,24 @@ describe SCNR::Engine::Parser::Document do
                     end
                 end
 
+                context 'when removing self-closing elements' do
+                    let(:html) do
+                        <<-EOHTML
+                    <html>
+                        <a name='x'/>
+                        <a href='/y'>y</a>
+                        <form></form>
+                    </html>
+                        EOHTML
+                    end
+
+                    it 'closes the next element of the same name' do
+                        expect(subject.css( 'html > a[href]' ).size).to eq 1
+                        expect(subject.css( 'html > form' ).size).to eq 1
+                        expect(subject.css( 'a form' )).to be_empty
+                    end
+                end
+
                 context 'Comment' do
                     let(:html) do
                         <<-EOHTML
@@ -566,6 +584,156 @@ describe SCNR::Engine::Parser::Document do
                     end
                 end
 
+                context 'button with formaction' do
+                    let(:html) do
+                        <<-EOHTML
+                            <button formaction="/stuff"></button>
+                        EOHTML
+                    end
+
+                    let(:expected) do
+                        "<!DOCTYPE html>\n<button formaction=\"/stuff\">\n</button>\n\n"
+                    end
+
+                    it 'includes it' do
+                        expect(subject.to_html).to eq expected
+                    end
+                end
+
+            end
+
+            context 'Symbol' do
+                let(:html) do
+                    <<-EOHTML
+                    <div>
+                        <script src="/script.js"></script>
+                        <script>var stuff;</script>
+                        <img srcset="/image.png 2x">
+                        <script type="application/ld+json">{"@type": "Thing"}</script>
+                    </div>
+                    EOHTML
+                end
+
+                context ':paths' do
+                    let(:filter){ :paths }
+
+                    let(:expected) do
+                        "<!DOCTYPE html>\n<script src=\"/script.js\">\n</script>\n<img srcset=\"/image.png 2x\" />\n\n"
+                    end
+
+                    it 'keeps elements that point to other resources' do
+                        expect(subject.to_html).to eq expected
+                    end
+                end
+
+                context ':json_ld' do
+                    let(:filter){ :json_ld }
+
+                    let(:expected) do
+                        "<!DOCTYPE html>\n<script type=\"application/ld+json\">\n    {\"@type\": \"Thing\"}\n</script>\n\n"
+                    end
+
+                    it 'keeps JSON-LD scripts and their text' do
+                        expect(subject.to_html).to eq expected
+                    end
+                end
+
+                context 'when unknown' do
+                    let(:filter){ :stuff }
+
+                    it 'raises ArgumentError' do
+                        expect { subject }.to raise_error ArgumentError
+                    end
+                end
+            end
+
+            context 'Hash' do
+                let(:html) do
+                    <<-EOHTML
+                    <div>
+                        <p class="keep">Stuff</p>
+                        <p>Other stuff</p>
+                    </div>
+                    EOHTML
+                end
+
+                let(:filter) do
+                    { elements: ['p[class=keep]'], text: ['p'] }
+                end
+
+                let(:expected) do
+                    "<!DOCTYPE html>\n<p class=\"keep\">\n    Stuff\n</p>\n\n"
+                end
+
+                it 'compiles a custom profile' do
+                    expect(subject.to_html).to eq expected
+                end
+
+                context 'with :others' do
+                    let(:filter) do
+                        { elements: ['p[class=keep]'], text: ['p'], others: true }
+                    end
+
+                    let(:expected) do
+                        "<!DOCTYPE html>\n<div>\n    <p class=\"keep\">\n        Stuff\n    </p>\n</div>\n\n"
+                    end
+
+                    it 'keeps elements without a rule' do
+                        expect(subject.to_html).to eq expected
+                    end
+                end
+
+                context 'with an invalid rule' do
+                    let(:filter) do
+                        { elements: ['p[class'] }
+                    end
+
+                    it 'raises ArgumentError' do
+                        expect { subject }.to raise_error ArgumentError
+                    end
+                end
+            end
+        end
+
+        describe 'spec_compliant', if: defined?( SCNR::Engine::Parser::Ext::Document ) do
+            subject { described_class.parse html, false, true }
+
+            let(:html) do
+                <<-EOHTML
+                <p>One
+                <p>Two <b>bold <i>both</b> italic</i>
+                <table>Stray<tr><td>Cell</table>
+                EOHTML
+            end
+
+            let(:expected) do
+                "<!DOCTYPE html>\n<html>\n    <head>\n    </head>\n    <body>\n" +
+                    "        <p>\n            One\n        </p>\n" +
+                    "        <p>\n            Two\n            <b>\n                bold\n" +
+                    "                <i>\n                    both\n                </i>\n            </b>\n" +
+                    "            <i>\n                italic\n            </i>\n            Stray\n" +
+                    "            <table>\n                <tbody>\n                    <tr>\n" +
+                    "                        <td>\n                            Cell\n                        </td>\n" +
+                    "                    </tr>\n                </tbody>\n            </table>\n" +
+                    "        </p>\n    </body>\n</html>\n\n"
+            end
+
+            it 'builds the tree the way browsers do' do
+                expect(subject.to_html).to eq expected
+            end
+
+            context 'when filtering' do
+                subject { described_class.parse html, :paths, true }
+
+                let(:html) do
+                    <<-EOHTML
+                    <p><a href="/one">One<p><a href="/two">Two</a>
+                    EOHTML
+                end
+
+                it 'keeps only what the profile allows' do
+                    expect(subject.to_html).to eq "<!DOCTYPE html>\n<a href=\"/one\">\n</a>\n<a href=\"/two\">\n</a>\n\n"
+                end
             end
         end
     end
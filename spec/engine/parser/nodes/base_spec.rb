@@ -154,4 +154,380 @@ describe SCNR::Engine::Parser::Nodes::Base do
         end
     end
 
+    describe '#css', if: defined?( SCNR::Engine::Parser::Ext::Document ) do
+        let(:html) do
+            <<-EOHTML
+        <html>
+            <form id='multipart' enctype='multipart/form-data'>
+                <input id='hidden' type='hidden'>
+                <input id='text' type='text'>
+                <div>
+                    <input id='nested-hidden' type='HIDDEN'>
+                </div>
+            </form>
+
+            <form id='plain'>
+                <input id='plain-hidden' type='hidden'>
+            </form>
+
+            <ul>
+                <li id='first' class='item odd'></li>
+                <li id='second' class='item' lang='en-US'></li>
+                <li id='third' class='item odd'></li>
+            </ul>
+        </html>
+            EOHTML
+        end
+
+        def ids( selector, node = subject )
+            node.css( selector ).map { |n| n['id'] }
+        end
+
+        it 'returns matching descendant nodes in document order' do
+            expect(ids( 'li' )).to eq %w(first second third)
+        end
+
+        it 'supports descendant combinators' do
+            expect(ids( "form[enctype^='multipart/'] input[type=hidden i]" )).to eq %w(hidden nested-hidden)
+        end
+
+        it 'supports child combinators' do
+            expect(ids( 'form > input[type=hidden]' )).to eq %w(hidden plain-hidden)
+        end
+
+        it 'supports sibling combinators' do
+            expect(ids( '#first + li' )).to eq %w(second)
+            expect(ids( '#first ~ li' )).to eq %w(second third)
+        end
+
+        it 'supports attribute operators' do
+            expect(ids( '[class~=odd]' )).to eq %w(first third)
+            expect(ids( '[lang|=en]' )).to eq %w(second)
+            expect(ids( '[id$=hidden]' )).to eq %w(hidden nested-hidden plain-hidden)
+            expect(ids( '[id*=ain]' )).to eq %w(plain plain-hidden)
+        end
+
+        it 'supports :not' do
+            expect(ids( 'input:not([type=text]):not(#plain-hidden)' )).to eq %w(hidden nested-hidden)
+        end
+
+        it 'supports :nth-child' do
+            expect(ids( 'li:nth-child(odd)' )).to eq %w(first third)
+            expect(ids( 'li:nth-child(-n+2)' )).to eq %w(first second)
+            expect(ids( 'li:nth-last-child(1)' )).to eq %w(third)
+        end
+
+        it 'supports selector groups' do
+            expect(ids( '#text, #first' )).to eq %w(text first)
+        end
+
+        it 'does not look above the node' do
+            form = subject.at_css( '#multipart' )
+
+            expect(ids( 'form input', form )).to be_empty
+            expect(ids( 'div input', form )).to eq %w(nested-hidden)
+        end
+
+        context 'when the selector is invalid' do
+            it 'raises ArgumentError' do
+                expect { subject.css( 'li:nth-child(x)' ) }.to raise_error ArgumentError
+            end
+        end
+    end
+
+    describe '#at_css', if: defined?( SCNR::Engine::Parser::Ext::Document ) do
+        it 'returns the first matching descendant node' do
+            expect(summarize( subject.at_css( 'span' ) )).to eq [:span, {"data-id"=>"deepest"}]
+        end
+
+        context 'when there is no match' do
+            it 'returns nil' do
+                expect(subject.at_css( 'form' )).to be_nil
+            end
+        end
+
+        context 'when the selector is invalid' do
+            it 'raises ArgumentError' do
+                expect { subject.at_css( 'a >' ) }.to raise_error ArgumentError
+            end
+        end
+    end
+
+    describe '#xpath', if: defined?( SCNR::Engine::Parser::Ext::Document ) do
+        let(:html) do
+            <<-EOHTML
+        <html>
+            <form id='login' action='/login'>
+                <input id='user' name='user' type='text'>
+                <input id='token' name='token' type='hidden'>
+                <div>
+                    <input id='nested' type='hidden'>
+                </div>
+            </form>
+
+            <ul>
+                <li id='first'>One</li>
+                <li id='second'>Two words</li>
+                <li id='third'>Three</li>
+            </ul>
+        </html>
+            EOHTML
+        end
+
+        def ids( expression, node = subject )
+            node.xpath( expression ).map { |n| n['id'] }
+        end
+
+        it 'supports location paths' do
+            expect(ids( '/html/form/input' )).to eq %w(user token)
+            expect(ids( '//form//input' )).to eq %w(user token nested)
+        end
+
+        it 'supports attribute predicates' do
+            expect(ids( "//input[@type='hidden']" )).to eq %w(token nested)
+            expect(ids( "//input[@name and @type!='text']" )).to eq %w(token)
+        end
+
+        it 'supports position predicates' do
+            expect(ids( '//li[2]' )).to eq %w(second)
+            expect(ids( '//li[last()]' )).to eq %w(third)
+            expect(ids( '//li[position() > 1]' )).to eq %w(second third)
+            expect(ids( '(//input)[3]' )).to eq %w(nested)
+        end
+
+        it 'supports text()' do
+            expect(subject.xpath( '//li/text()' ).map( &:text )).to eq ['One', 'Two words', 'Three']
+            expect(ids( "//li[text()='Three']" )).to eq %w(third)
+        end
+
+        it 'supports contains()' do
+            expect(ids( "//li[contains(text(), 'words')]" )).to eq %w(second)
+        end
+
+        it 'supports axes' do
+            expect(ids( "//input[@id='nested']/ancestor::form" )).to eq %w(login)
+            expect(ids( "//li[@id='second']/following-sibling::li" )).to eq %w(third)
+        end
+
+        it 'returns attributes as their values' do
+            expect(subject.xpath( '//form/@action' )).to eq ['/login']
+        end
+
+        context 'when given a relative path' do
+            it 'evaluates it against the node' do
+                form = subject.xpath( '//form' ).first
+                expect(ids( 'input', form )).to eq %w(user token)
+            end
+        end
+
+        context 'when the expression is invalid' do
+            it 'raises ArgumentError' do
+                expect { subject.xpath( '//li[' ) }.to raise_error ArgumentError
+            end
+        end
+
+        context 'when the expression does not select nodes' do
+            it 'raises ArgumentError' do
+                expect { subject.xpath( 'count(//li)' ) }.to raise_error ArgumentError
+            end
+        end
+    end
+
+    describe '#position', if: defined?( SCNR::Engine::Parser::Ext::Document ) do
+        subject { SCNR::Engine::Parser.parse( html, positions: true ) }
+        let(:html) do
+            "<html>\n    <form action='/login'>\n        <input name='ü'>Some text\n    </form>\n</html>"
+        end
+
+        it 'returns the byte offset, line and column where the node starts' do
+            expect(subject.at_css( 'input' ).position).to eq(
+                offset: html.b.index( '<input' ),
+                line:   3,
+                column: 9
+            )
+        end
+
+        it 'counts columns in characters' do
+            text = subject.at_css( 'form' ).xpath( 'text()' ).first
+
+            expect(text.position).to eq(
+                offset: html.b.index( 'Some text' ),
+                line:   3,
+                column: 25
+            )
+        end
+
+        context 'for the document' do
+            it 'returns nil' do
+                expect(subject.position).to be_nil
+            end
+        end
+
+        context 'when not parsed with :positions' do
+            it 'returns nil' do
+                expect(SCNR::Engine::Parser.parse( html ).at_css( 'input' ).position).to be_nil
+            end
+        end
+    end
+
+    describe '#source_range', if: defined?( SCNR::Engine::Parser::Ext::Document ) do
+        subject { SCNR::Engine::Parser.parse( html, positions: true ) }
+        let(:html) do
+            "<html>\n    <form action='/login'>\n        <input name='ü'>Some text\n    </form>\n</html>"
+        end
+
+        it 'spans the element from its start tag to its end tag' do
+            range = subject.at_css( 'form' ).source_range
+
+            expect(range[:start]).to eq( offset: html.b.index( '<form' ), line: 2, column: 5 )
+            expect(range[:end]).to eq( offset: html.b.index( '</form>' ) + 7, line: 4, column: 12 )
+        end
+
+        it 'spans void elements' do
+            range = subject.at_css( 'input' ).source_range
+
+            expect(html.b[range[:start][:offset]...range[:end][:offset]]).to eq "<input name='ü'>".b
+        end
+
+        context 'when not parsed with :positions' do
+            it 'returns nil' do
+                expect(SCNR::Engine::Parser.parse( html ).at_css( 'form' ).source_range).to be_nil
+            end
+        end
+    end
+
+    describe '#forms', if: defined?( SCNR::Engine::Parser::Ext::Document ) do
+        let(:html) do
+            <<-EOHTML
+            <form id="login" action="/login" method="POST" data-stuff="1">
+                <input name="user" value="john">
+                <input type="Checkbox" name="remember" checked>
+                <select name="lang">
+                    <option value="en">English</option>
+                    <option value="fr" selected>French</option>
+                </select>
+                <textarea name="bio">Hi</textarea>
+                <button name="go">Go</button>
+            </form>
+
+            <form>
+                <select name="empty"><option>First</option><option>Second</option></select>
+            </form>
+
+            <input name="outside" form="login">
+            <input name="orphan">
+            EOHTML
+        end
+
+        let(:forms) { subject.forms }
+
+        it 'returns all forms' do
+            expect(forms.size).to eq 2
+        end
+
+        it 'includes form attributes' do
+            expect(forms[0]).to include(
+                action:     '/login',
+                method:     :post,
+                enctype:    'application/x-www-form-urlencoded',
+                id:         'login',
+                name:       nil,
+                attributes: {
+                    'id'         => 'login',
+                    'action'     => '/login',
+                    'method'     => 'POST',
+                    'data-stuff' => '1'
+                }
+            )
+        end
+
+        it 'defaults to GET' do
+            expect(forms[1]).to include( action: nil, method: :get )
+        end
+
+        it 'includes fields with their default values' do
+            fields = forms[0][:fields].map { |f| [f[:tag], f[:name], f[:type], f[:value]] }
+
+            expect(fields).to eq [
+                [:input, 'user', :text, 'john'],
+                [:input, 'remember', :checkbox, ''],
+                [:select, 'lang', :select, 'fr'],
+                [:textarea, 'bio', :textarea, 'Hi'],
+                [:button, 'go', :submit, ''],
+                [:input, 'outside', :text, '']
+            ]
+        end
+
+        it 'includes the checked state' do
+            expect(forms[0][:fields][1][:checked]).to be true
+            expect(forms[0][:fields][0][:checked]).to be false
+        end
+
+        it 'includes select options' do
+            expect(forms[0][:fields][2][:options]).to eq [
+                { value: 'en', text: 'English', selected: false },
+                { value: 'fr', text: 'French',  selected: true }
+            ]
+        end
+
+        context 'when no option is selected' do
+            it 'defaults to the first one' do
+                expect(forms[1][:fields][0][:value]).to eq 'First'
+            end
+        end
+
+        it 'ignores fields that belong to no form' do
+            names = forms.map { |f| f[:fields].map { |field| field[:name] } }.flatten
+            expect(names).not_to include 'orphan'
+        end
+    end
+
+    describe '#paths', if: defined?( SCNR::Engine::Parser::Ext::Document ) do
+        let(:html) do
+            <<-EOHTML
+            <html manifest="/app.appcache">
+                <head>
+                    <base href="/base/">
+                    <link rel="stylesheet" href="/style.css">
+                    <meta http-equiv="Refresh" content="5; URL='/refreshed'">
+                    <script src="/app.js">var data = "/data/stuff.json";</script>
+                </head>
+                <body>
+                    <!-- see /hidden/path, not / -->
+                    <a href="/a" ping="/ping-1 /ping-2">A</a>
+                    <map><area href="/area"></map>
+                    <form action="/submit"><button formaction="/other">Go</button></form>
+                    <iframe src="/frame"></iframe>
+                    <img srcset="/small.png 1x, /large.png 2x">
+                    <video poster="/poster.jpg"></video>
+                    <object data="/movie.swf"></object>
+                </body>
+            </html>
+            EOHTML
+        end
+
+        it 'returns paths tagged with the extractor that found them' do
+            expect(subject.paths).to eq [
+                ['/app.appcache', :manifest],
+                ['/base/', :base],
+                ['/style.css', :links],
+                ['/refreshed', :meta_refresh],
+                ['/app.js', :scripts],
+                ['/data/stuff.json', :scripts],
+                ['/hidden/path', :comments],
+                ['/a', :anchors],
+                ['/ping-1', :ping],
+                ['/ping-2', :ping],
+                ['/area', :areas],
+                ['/submit', :forms],
+                ['/other', :formaction],
+                ['/frame', :frames],
+                ['/small.png', :srcset],
+                ['/large.png', :srcset],
+                ['/poster.jpg', :poster],
+                ['/movie.swf', :data]
+            ]
+        end
+    end
+
 end
@@ -0,0 +1,79 @@
+require 'spec_helper'
+
+if SCNR::Engine.has_extension?
+    describe SCNR::Engine::Parser::Ext::PushParser do
+        subject { described_class.new }
+        let(:html) do
+            <<-EOHTML
+        <html>
+            <form action='/stüff'>
+                <input name='token' value='ü'>
+                Some text
+            </form>
+        </html>
+            EOHTML
+        end
+
+        describe '#<<' do
+            it 'returns self' do
+                expect(subject << '<html>').to be subject
+            end
+
+            it 'parses the document in chunks' do
+                html.b.bytes.each_slice( 3 ) { |chunk| subject << chunk.pack( 'C*' ) }
+
+                expect(subject.finish.to_html).to eq SCNR::Engine::Parser::Ext::Document.parse( html ).to_html
+            end
+
+            context 'when the parser has finished' do
+                it 'raises RuntimeError' do
+                    subject.finish
+                    expect { subject << '<html>' }.to raise_error RuntimeError
+                end
+            end
+        end
+
+        describe '#document' do
+            it 'returns the document as parsed so far' do
+                subject << "<form><input name='token'>"
+
+                inputs = []
+                subject.document.nodes_by_name( :input ) { |n| inputs << n['name'] }
+                expect(inputs).to eq %w(token)
+            end
+        end
+
+        context 'when tracking positions' do
+            subject { described_class.new( false, true ) }
+
+            it 'locates nodes across chunks' do
+                html.b.bytes.each_slice( 3 ) { |chunk| subject << chunk.pack( 'C*' ) }
+
+                input = subject.finish.at_css( 'input' )
+                expect(input.position[:offset]).to eq html.b.index( '<input' )
+            end
+        end
+
+        context 'when not tracking positions' do
+            it 'does not locate nodes' do
+                subject << html
+                expect(subject.finish.at_css( 'input' ).position).to be_nil
+            end
+        end
+
+        describe '#finished?' do
+            context 'before #finish' do
+                it 'returns false' do
+                    expect(subject).to_not be_finished
+                end
+            end
+
+            context 'after #finish' do
+                it 'returns true' do
+                    subject.finish
+                    expect(subject).to be_finished
+                end
+            end
+        end
+    end
+end
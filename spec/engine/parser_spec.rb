@@ -520,6 +520,64 @@ describe SCNR::Engine::Parser do
             end
         end
 
+        context 'when the document supports native path extraction',
+                if: defined?( SCNR::Engine::Parser::Ext::Document ) do
+            let(:response) do
+                SCNR::Engine::HTTP::Response.new(
+                    url: root_url,
+                    body: '
+                <html>
+                    <body>
+                        <a href="/anchor" ping="/ping"></a>
+                        <img srcset="/image.png 2x">
+                    </body>
+                </html>'
+                )
+            end
+
+            it 'includes attributes the extractors miss' do
+                expect(subject.paths.sort).to eq(
+                    %w(anchor image.png ping).map { |p| root_url + p }
+                )
+            end
+
+            context 'when a button only has a formaction' do
+                let(:response) do
+                    SCNR::Engine::HTTP::Response.new(
+                        url: root_url,
+                        body: '
+                    <html>
+                        <body>
+                            <form action="/submit"><button formaction="/other">Go</button></form>
+                        </body>
+                    </html>'
+                    )
+                end
+
+                it 'includes it' do
+                    expect(subject.paths).to include root_url + 'other'
+                end
+            end
+
+            context 'when a data-url attribute is in a script' do
+                let(:response) do
+                    SCNR::Engine::HTTP::Response.new(
+                        url: root_url,
+                        body: '
+                    <html>
+                        <body>
+                            <script>el.innerHTML = "<div data-url=\'/from-script\'>";</script>
+                        </body>
+                    </html>'
+                    )
+                end
+
+                it 'includes it' do
+                    expect(subject.paths).to include root_url + 'from-script'
+                end
+            end
+        end
+
         context 'when an error occurs' do
             it 'returns an empty array' do
                 allow(described_class).to receive(:extractors){ raise }
@@ -678,6 +736,23 @@ describe SCNR::Engine::Parser do
         end
     end
 
+    describe '.push_parser' do
+        if SCNR::Engine.has_extension?
+            context 'when the extension is loaded' do
+                it "returns #{SCNR::Engine::Parser::Document}.push_parser" do
+                    parser = described_class.push_parser( filter: true )
+                    expect(parser).to be_kind_of SCNR::Engine::Parser::Ext::PushParser
+
+                    parser << "<form action='/stuff'><input name='token'>"
+
+                    inputs = []
+                    parser.finish.nodes_by_name( :input ) { |n| inputs << n['name'] }
+                    expect(inputs).to eq %w(token)
+                end
+            end
+        end
+    end
+
     describe '.markup?' do
         context 'when dealing with markup' do
             it 'returns true' do
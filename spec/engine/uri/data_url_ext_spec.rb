@@ -0,0 +1,86 @@
+require 'spec_helper'
+
+if !SCNR::Engine.windows?
+    describe SCNR::Engine::Rust::URI::DataURL do
+        subject { described_class.new( url ) }
+        let(:url) { 'data:text/HTML;charset=UTF-8;base64,PGEgaHJlZj0iL3N0dWZmIj5hPC9hPg==' }
+
+        describe '.new' do
+            context 'when the URL is invalid' do
+                it 'raises ArgumentError' do
+                    expect { described_class.new( 'data:text/plain' ) }.to raise_error ArgumentError
+                end
+            end
+        end
+
+        describe '.parse' do
+            context 'when the URL is invalid' do
+                it 'returns nil' do
+                    expect(described_class.parse( 'http://test.com/' )).to be_nil
+                    expect(described_class.parse( 'data:;base64,S' )).to be_nil
+                end
+            end
+        end
+
+        describe '#mime_type' do
+            it 'returns the lowercase MIME type' do
+                expect(subject.mime_type).to eq('text/html')
+            end
+
+            context 'when there is no MIME type' do
+                let(:url) { 'data:,stuff' }
+
+                it 'defaults to text/plain' do
+                    expect(subject.mime_type).to eq('text/plain')
+                end
+            end
+        end
+
+        describe '#charset' do
+            it 'returns the charset' do
+                expect(subject.charset).to eq('UTF-8')
+            end
+
+            context 'when there is no MIME type' do
+                let(:url) { 'data:,stuff' }
+
+                it 'defaults to US-ASCII' do
+                    expect(subject.charset).to eq('US-ASCII')
+                end
+            end
+        end
+
+        describe '#base64?' do
+            it 'returns true for base64 payloads' do
+                expect(subject).to be_base64
+                expect(described_class.new( 'data:,stuff' )).to_not be_base64
+            end
+        end
+
+        describe '#data' do
+            it 'returns the decoded payload' do
+                expect(subject.data).to eq('<a href="/stuff">a</a>')
+                expect(subject.data.encoding).to eq(Encoding::BINARY)
+            end
+
+            context 'when the payload is percent-encoded' do
+                let(:url) { 'data:,Hello%2C%20World%21' }
+
+                it 'decodes it' do
+                    expect(subject.data).to eq('Hello, World!')
+                end
+            end
+        end
+
+        describe '#document?' do
+            it 'returns true for HTML and SVG' do
+                expect(subject).to be_document
+                expect(described_class.new( 'data:image/svg+xml,<svg></svg>' )).to be_document
+            end
+
+            it 'returns false for other types' do
+                expect(described_class.new( 'data:image/png;base64,iVBORw0K' )).to_not be_document
+            end
+        end
+    end
+end
@@ -0,0 +1,106 @@
+require 'spec_helper'
+
+if !SCNR::Engine.windows?
+    describe SCNR::Engine::Rust::URI::Rewrite do
+        subject { described_class.new( rules ) }
+
+        let(:rules) do
+            {
+                'nothing-to-see-here'    => 'nope',
+                'articles/[\w-]+/(\d+)' => 'articles.php?id=\1',
+                'users/(?<name>\w+)'     => 'users.php?name=\k<name>'
+            }
+        end
+
+        describe '#apply' do
+            it 'rewrites the URL in place' do
+                url = SCNR::Engine::URIExt.new( 'http://test.com/articles/some-stuff/23' )
+                subject.apply( url )
+
+                expect(url.to_s).to eq('http://test.com/articles.php?id=23')
+            end
+
+            it 'returns the index of the rule that fired' do
+                url = SCNR::Engine::URIExt.new( 'http://test.com/users/john' )
+
+                expect(subject.apply( url )).to eq(2)
+                expect(url.to_s).to eq('http://test.com/users.php?name=john')
+            end
+
+            it 'matches case-insensitively' do
+                url = SCNR::Engine::URIExt.new( 'http://test.com/Articles/some-stuff/23' )
+                expect(subject.apply( url )).to eq(1)
+            end
+
+            context 'when no rule applies' do
+                it 'returns nil' do
+                    url = SCNR::Engine::URIExt.new( 'http://test.com/stuff' )
+
+                    expect(subject.apply( url )).to be_nil
+                    expect(url.to_s).to eq('http://test.com/stuff')
+                end
+            end
+        end
+
+        describe '#rules' do
+            it 'returns the rules in order' do
+                expect(subject.rules).to eq(rules.to_a)
+            end
+        end
+
+        context 'when given Regexp patterns' do
+            let(:rules) do
+                { /Articles\/(\d+)/ => 'articles.php?id=\1' }
+            end
+
+            it 'keeps their flags' do
+                url = SCNR::Engine::URIExt.new( 'http://test.com/articles/23' )
+                expect(subject.apply( url )).to be_nil
+
+                url = SCNR::Engine::URIExt.new( 'http://test.com/Articles/23' )
+                expect(subject.apply( url )).to eq(0)
+                expect(url.to_s).to eq('http://test.com/articles.php?id=23')
+            end
+
+            it 'returns them as given' do
+                expect(subject.rules).to eq(rules.to_a)
+            end
+        end
+
+        context 'when given the scope options' do
+            before do
+                SCNR::Engine::Options.scope.url_rewrites = {
+                    'articles/[\w-]+/(\d+)' => 'articles.php?id=\1'
+                }
+            end
+
+            let(:rules) { SCNR::Engine::Options.scope.url_rewrites }
+
+            it 'uses their rules' do
+                url = SCNR::Engine::URIExt.new( 'http://test.com/articles/some-stuff/23' )
+
+                expect(subject.apply( url )).to eq(0)
+                expect(url.to_s).to eq('http://test.com/articles.php?id=23')
+            end
+        end
+
+        context 'when a pattern uses Ruby-only syntax' do
+            let(:rules) do
+                { /(?<=users\/)(\w+)(?!\.php)$/ => '\1.php' }
+            end
+
+            it 'falls back to Ruby' do
+                url = SCNR::Engine::URIExt.new( 'http://test.com/users/john' )
+
+                expect(subject.apply( url )).to eq(0)
+                expect(url.to_s).to eq('http://test.com/users/john.php')
+            end
+        end
+
+        context 'when given an invalid pattern' do
+            it "raises #{RegexpError}" do
+                expect { described_class.new( '(' => 'stuff' ) }.to raise_error RegexpError
+            end
+        end
+    end
+end
@@ -0,0 +1,206 @@
+require 'spec_helper'
+
+if !SCNR::Engine.windows?
+    describe SCNR::Engine::Rust::URI::Scope do
+        subject { described_class.new( options ) }
+
+        let(:scope) { SCNR::Engine::Options.scope }
+        let(:options) { scope.to_native_data.merge( 'url' => 'https://example.com/' ) }
+
+        def uri( url )
+            SCNR::Engine::URIExt.new( url )
+        end
+
+        describe '#too_deep?' do
+            let(:url) { uri( 'https://example.com/very/very/very/very/deep' ) }
+
+            context 'when the directory depth of the URL is' do
+                context "less than #{SCNR::Engine::OptionGroups::Scope}#directory_depth_limit" do
+                    it 'returns false' do
+                        scope.directory_depth_limit = 100
+                        expect(subject.too_deep?( url, nil )).to be_falsey
+                    end
+                end
+
+                context "more than #{SCNR::Engine::OptionGroups::Scope}#directory_depth_limit" do
+                    it 'returns true' do
+                        scope.directory_depth_limit = 2
+                        expect(subject.too_deep?( url, nil )).to be_truthy
+                    end
+                end
+            end
+
+            context "when the crawl depth reaches #{SCNR::Engine::OptionGroups::Scope}#depth_limit" do
+                it 'returns true' do
+                    scope.depth_limit = 2
+                    expect(subject.too_deep?( url, 2 )).to be_truthy
+                end
+            end
+        end
+
+        describe '#in_domain?' do
+            it 'compares the hosts' do
+                expect(subject.in_domain?( uri( 'https://example.com/stuff' ) )).to be_truthy
+                expect(subject.in_domain?( uri( 'https://sub.example.com/stuff' ) )).to be_falsey
+            end
+
+            context "when #{SCNR::Engine::OptionGroups::Scope}#include_subdomains is set" do
+                it 'compares the domains' do
+                    scope.include_subdomains = true
+                    expect(subject.in_domain?( uri( 'https://sub.example.com/stuff' ) )).to be_truthy
+                end
+            end
+
+            context 'when the seed URL is under a multi-label public suffix' do
+                let(:options) { scope.to_native_data.merge( 'url' => 'https://example.co.uk/' ) }
+
+                it 'compares the registrable domains' do
+                    scope.include_subdomains = true
+                    expect(subject.in_domain?( uri( 'https://www.example.co.uk/' ) )).to be_truthy
+                    expect(subject.in_domain?( uri( 'https://other.co.uk/' ) )).to be_falsey
+                end
+
+                context "and #{SCNR::Engine::OptionGroups::Scope}#registrable_domains is missing" do
+                    let(:options) do
+                        scope.to_native_data.merge( 'url' => 'https://example.co.uk/' ).
+                            tap { |o| o.delete( 'registrable_domains' ) }
+                    end
+
+                    it 'compares the registrable domains' do
+                        scope.include_subdomains = true
+                        expect(subject.in_domain?( uri( 'https://other.co.uk/' ) )).to be_falsey
+                    end
+                end
+            end
+        end
+
+        describe '#follow_protocol?' do
+            context "when #{SCNR::Engine::OptionGroups::Scope}#https_only is set" do
+                it 'rejects HTTP URLs' do
+                    scope.https_only = true
+                    expect(subject.follow_protocol?( uri( 'http://example.com/' ) )).to be_falsey
+                end
+            end
+
+            context "when #{SCNR::Engine::OptionGroups::Scope}#https_only is not set" do
+                it 'accepts HTTP URLs' do
+                    expect(subject.follow_protocol?( uri( 'http://example.com/' ) )).to be_truthy
+                end
+            end
+        end
+
+        describe '#exclude?' do
+            it 'checks the file extension' do
+                expect(subject.exclude?( uri( 'https://example.com/image.PNG' ) )).to be_truthy
+            end
+
+            it 'checks the exclude patterns' do
+                scope.exclude_path_patterns = 'logout'
+                expect(subject.exclude?( uri( 'https://example.com/logout' ) )).to be_truthy
+                expect(subject.exclude?( uri( 'https://example.com/login' ) )).to be_falsey
+            end
+
+            it 'respects the flags of Regexp patterns' do
+                scope.exclude_path_patterns = /Logout/
+                expect(subject.exclude?( uri( 'https://example.com/Logout' ) )).to be_truthy
+                expect(subject.exclude?( uri( 'https://example.com/logout' ) )).to be_falsey
+            end
+
+            context 'when a pattern uses syntax only Ruby supports' do
+                it 'matches it with Ruby' do
+                    scope.exclude_path_patterns = /log(?!in)/
+                    expect(subject.exclude?( uri( 'https://example.com/logout' ) )).to be_truthy
+                    expect(subject.exclude?( uri( 'https://example.com/login' ) )).to be_falsey
+                end
+            end
+
+            context 'when given pattern sources' do
+                let(:options) { scope.to_rpc_data.merge( 'url' => 'https://example.com/' ) }
+
+                it 'matches them case-insensitively' do
+                    scope.exclude_path_patterns = /Logout/
+                    expect(subject.exclude?( uri( 'https://example.com/logout' ) )).to be_truthy
+                end
+            end
+        end
+
+        describe '#include?' do
+            it 'checks the include patterns' do
+                scope.include_path_patterns = 'login'
+                expect(subject.include?( uri( 'https://example.com/login' ) )).to be_truthy
+                expect(subject.include?( uri( 'https://example.com/logout' ) )).to be_falsey
+            end
+        end
+
+        describe '#redundant?' do
+            let(:url) { uri( 'https://example.com/match_this' ) }
+
+            context 'when the update_counters option is' do
+                context 'true' do
+                    it 'decrements the counters' do
+                        scope.redundant_path_patterns = { /match_this/ => 2 }
+
+                        expect(subject.redundant?( url, true )).to be_falsey
+                        expect(subject.redundant?( url, true )).to be_falsey
+                        expect(subject.redundant?( url, true )).to be_truthy
+                    end
+                end
+
+                context 'false' do
+                    it 'does not decrement the counters' do
+                        scope.redundant_path_patterns = { /match_this/ => 1 }
+
+                        expect(subject.redundant?( url, false )).to be_falsey
+                        expect(subject.redundant?( url, false )).to be_falsey
+                    end
+                end
+            end
+
+            context "when #{SCNR::Engine::OptionGroups::Scope}#auto_redundant_paths is set" do
+                it 'limits URLs with the same parameter names' do
+                    scope.auto_redundant_paths = 1
+
+                    expect(subject.redundant?( uri( 'https://example.com/?id=1' ), true )).to be_falsey
+                    expect(subject.redundant?( uri( 'https://example.com/?id=2' ), true )).to be_truthy
+                end
+            end
+
+            it "shares the counters with #{SCNR::Engine::URI::Scope}" do
+                scope.redundant_path_patterns = { /match_this/ => 1 }
+                scope.auto_redundant_paths    = 1
+
+                expect(subject.redundant?( url, true )).to be_falsey
+                expect(scope.redundant_path_patterns.values).to eq [0]
+
+                expect(subject.redundant?( uri( 'https://example.com/?id=1' ), true )).to be_falsey
+                expect(SCNR::Engine::URI( 'https://example.com/?id=2' ).scope.auto_redundant?).to be_truthy
+            end
+        end
+
+        describe '#shape_redundant?' do
+            it 'limits URLs with the same shape' do
+                scope.auto_redundant_shapes = 1
+
+                expect(subject.shape_redundant?( uri( 'https://example.com/item/1?id=1' ), true )).to be_falsey
+                expect(subject.shape_redundant?( uri( 'https://example.com/item/2?id=2' ), true )).to be_truthy
+                expect(subject.shape_redundant?( uri( 'https://example.com/other/2?id=2' ), true )).to be_falsey
+            end
+        end
+
+        describe '#out?' do
+            it 'returns true for out of scope URLs' do
+                expect(subject.out?( uri( 'https://other.com/' ), nil )).to be_truthy
+            end
+
+            it 'returns false for in scope URLs' do
+                expect(subject.out?( uri( 'https://example.com/page' ), nil )).to be_falsey
+            end
+        end
+
+        describe '#in?' do
+            it 'returns true for in scope URLs' do
+                expect(subject.in?( uri( 'https://example.com/page' ), nil )).to be_truthy
+            end
+        end
+    end
+end
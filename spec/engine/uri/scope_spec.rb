@@ -178,6 +178,29 @@ describe SCNR::Engine::URI::Scope do
         end
     end
 
+    describe '#shape_redundant?' do
+        subject { SCNR::Engine::URI( 'http://test.com/item/1?id=1' ).scope }
+
+        if SCNR::Engine::URI.method_defined?( :shape_hash )
+            context "when #{SCNR::Engine::OptionGroups::Scope}#auto_redundant_shapes limit has been reached" do
+                it 'returns true' do
+                    scope.auto_redundant_shapes = 2
+
+                    expect(SCNR::Engine::URI( 'http://test.com/item/2?id=2' ).scope.shape_redundant?( true )).to be_falsey
+                    expect(SCNR::Engine::URI( 'http://test.com/item/3?id=3' ).scope.shape_redundant?( true )).to be_falsey
+
+                    expect(subject.shape_redundant?( true )).to be_truthy
+                end
+            end
+        end
+
+        describe 'by default' do
+            it 'returns false' do
+                expect(subject.shape_redundant?).to be_falsey
+            end
+        end
+    end
+
     describe '#exclude?' do
         let(:url) { SCNR::Engine::URI.parse( 'http://test.com/exclude/' ) }
         subject { url.scope }
@@ -404,12 +427,36 @@ describe SCNR::Engine::URI::Scope do
                 end
             end
         end
+
+        context 'when the seed URL is under a multi-label public suffix',
+                if: SCNR::Engine::URI.method_defined?( :registrable_domain ) do
+            before :each do
+                scope.include_subdomains = true
+                SCNR::Engine::Options.url = 'http://example.co.uk'
+            end
+
+            let(:other_site) { SCNR::Engine::URI.parse( 'http://other.co.uk' ).scope }
+            let(:subdomain) { SCNR::Engine::URI.parse( 'http://www.example.co.uk' ).scope }
+
+            it 'compares registrable domains' do
+                expect(subdomain.in_domain?).to be_truthy
+                expect(other_site.in_domain?).to be_falsey
+            end
+
+            context "and #{SCNR::Engine::OptionGroups::Scope}#registrable_domains is false" do
+                it 'compares domains' do
+                    scope.registrable_domains = false
+                    expect(other_site.in_domain?).to be_truthy
+                end
+            end
+        end
     end
 
     describe '#follow_protocol?' do
         let(:http) { SCNR::Engine::URI.parse( 'http://test2.com/blah/ha' ).scope }
         let(:https) { SCNR::Engine::URI.parse( 'https://test2.com/blah/ha' ).scope }
         let(:other) { SCNR::Engine::URI.parse( 'stuff://test2.com/blah/ha' ).scope }
+        let(:wss) { SCNR::Engine::URI.parse( 'wss://test2.com/socket' ).scope }
 
         context 'when the reference URL uses' do
             context 'HTTPS' do
@@ -436,6 +483,17 @@ describe SCNR::Engine::URI::Scope do
                         end
                     end
 
+                    context 'WSS' do
+                        context 'and Options#scope_https_only is' do
+                            context 'true' do
+                                it 'returns true' do
+                                    scope.https_only = true
+                                    expect(wss.follow_protocol?).to be_truthy
+                                end
+                            end
+                        end
+                    end
+
                     context 'HTTP' do
                         context 'and Options#scope_https_only is' do
                             context 'true' do
@@ -0,0 +1,90 @@
+require 'spec_helper'
+
+if !SCNR::Engine.windows?
+    describe SCNR::Engine::Rust::URI::TemplateInferencer do
+        let(:urls) do
+            %w(
+                http://test.com/user/1/profile
+                http://test.com/user/22/profile
+                http://test.com/user/333/profile
+                http://test.com/about/us
+                http://test.com/blog/a-post/
+                http://test.com/blog/b-post/
+                http://test.com/blog/c-post/
+                http://test.com/blog/d-post/
+                http://test.com/blog/e-post/
+            ).map { |url| SCNR::Engine::URIExt.new( url ) }
+        end
+
+        before do
+            subject.push_all( urls )
+        end
+
+        describe '#templates' do
+            it 'clusters URLs into templates' do
+                expect(subject.templates.map { |t| t['template'] }).to eq([
+                    'http://test.com/blog/{str}/',
+                    'http://test.com/user/{int}/profile'
+                ])
+            end
+
+            it 'includes the amount of samples' do
+                expect(subject.templates.map { |t| t['samples'] }).to eq([5, 3])
+            end
+
+            it 'includes a confidence score' do
+                expect(subject.templates.map { |t| t['confidence'] }).to eq([0.96875, 0.875])
+            end
+        end
+
+        describe '#patterns' do
+            it 'returns LinkTemplate patterns' do
+                template, inputs = SCNR::Engine::Element::LinkTemplate.extract_inputs(
+                    'http://test.com/user/55/profile?stuff=1',
+                    subject.patterns( 0 )
+                )
+
+                expect(template).to be_kind_of Regexp
+                expect(inputs).to eq( 'user' => '55' )
+            end
+
+            it 'escapes literal segments' do
+                subject.clear
+                subject.push_all(
+                    %w(http://test.com/v1.0/1 http://test.com/v1.0/2).map { |url| SCNR::Engine::URIExt.new( url ) }
+                )
+
+                expect(subject.patterns( 0 ).first).to match 'http://test.com/v1.0/3'
+                expect(subject.patterns( 0 ).first).to_not match 'http://test.com/v1x0/3'
+            end
+
+            it 'skips templates below the given confidence' do
+                expect(subject.patterns( 0.9 ).size).to eq(1)
+            end
+        end
+
+        describe '#push_all' do
+            context 'when given something other than URIExt objects' do
+                it "raises #{TypeError}" do
+                    expect { subject.push_all( ['http://test.com/user/1/profile'] ) }.to raise_error TypeError
+                end
+            end
+        end
+
+        describe '#<<' do
+            it 'adds a URL' do
+                subject << SCNR::Engine::URIExt.new( 'http://test.com/user/4444/profile' )
+                expect(subject.templates.last['samples']).to eq(4)
+            end
+        end
+
+        describe '#clear' do
+            it 'removes all URLs' do
+                subject.clear
+
+                expect(subject.size).to eq(0)
+                expect(subject.templates).to be_empty
+            end
+        end
+    end
+end
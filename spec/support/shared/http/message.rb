@@ -42,6 +42,11 @@ shared_examples_for 'SCNR::Engine::HTTP::Message' do
             expect(r.url).to be_frozen
         end
 
+        it 'keeps dot segments' do
+            subject.url = "#{url}/a/../../etc/passwd"
+            expect(subject.url).to eq("#{url}/a/../../etc/passwd")
+        end
+
         context 'when :normalize_url is' do
             context 'not given' do
                 it 'normalizes it' do
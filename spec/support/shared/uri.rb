@@ -85,7 +85,11 @@ shared_examples_for 'uri' do
            "http://foo.com/user/login?user%5Bname%5D=bar&user%5Bpass%5D=asdasd%26asdihbasd"=>
                "http://foo.com/user/login?user%5Bname%5D=bar&user%5Bpass%5D=asdasd%26asdihbasd",
            "http://stuff.host.fdfd/web/seguros/auto;jsessionid=6CB5A6A4597FFFA80C4D23B235072588.000?test=tet"=>
-               "http://stuff.host.fdfd/web/seguros/auto?test=tet",
+               # URIExt keeps path parameters as inputs, it only ignores
+               # session IDs when hashing; URIRuby drops them.
+               (described_class == SCNR::Engine::URIExt ?
+                   "http://stuff.host.fdfd/web/seguros/auto;jsessionid=6CB5A6A4597FFFA80C4D23B235072588.000?test=tet" :
+                   "http://stuff.host.fdfd/web/seguros/auto?test=tet"),
             'http://127.0.0.2:51453/link-template/append/input/default%23%5E($!@$)(()))******/stuff' =>
                 'http://127.0.0.2:51453/link-template/append/input/default%23%5E($!@$)(()))******/stuff'
         }
@@ -206,6 +210,25 @@ AAAFCAYAAACNbyblAAAAHElEQVQI12P4//8/w38GIAXDIBKE0DHxgljNBAAO
 AAAFCAYAAACNbyblAAAAHElEQVQI12P4//8/w38GIAXDIBKE0DHxgljNBAAO
 9TXL0Y4OHwAAAABJRU5ErkJggg==' )).to be_nil
         end
+
+        it 'only removes the default port of the scheme' do
+            expect(described_class.parse( 'https://test.com:80/' ).to_s).to eq('https://test.com:80/')
+            expect(described_class.parse( 'http://test.com:443/' ).to_s).to eq('http://test.com:443/')
+            expect(described_class.parse( 'https://test.com:443/' ).port).to be_nil
+        end
+
+        context 'when the URL is schemeless' do
+            it 'removes port 80' do
+                expect(described_class.parse( '//test.com:80/stuff' ).port).to be_nil
+                expect(described_class.parse( '//test.com:8080/stuff' ).port).to eq(8080)
+            end
+        end
+
+        it 'parses WebSocket URLs' do
+            expect(described_class.parse( 'ws://test.com:80/socket' ).to_s).to eq('ws://test.com/socket')
+            expect(described_class.parse( 'wss://test.com:443/socket?a=1' ).to_s).to eq('wss://test.com/socket?a=1')
+            expect(described_class.parse( 'wss://test.com:8443/socket' ).port).to eq(8443)
+        end
     end
 
     describe '.to_absolute' do
@@ -291,6 +314,7 @@ AAAFCAYAAACNbyblAAAAHElEQVQI12P4//8/w38GIAXDIBKE0DHxgljNBAAO
             @urls.each do |url|
                 uri = described_class.new( url )
                 expect(uri.is_a?( described_class )).to be_truthy
+
                 expect(uri.to_s).to eq(@ref_normalizer.call( url ))
             end
         end
@@ -477,9 +501,12 @@ AAAFCAYAAACNbyblAAAAHElEQVQI12P4//8/w38GIAXDIBKE0DHxgljNBAAO
     end
 
     describe '#_dump' do
-        it 'returns the URL as a string' do
-            uri = 'http://test.com/?my=val'
-            expect(described_class.new( uri )._dump(nil)).to eq(uri)
+        # URIExt dumps its parsed fields in a binary format instead.
+        if described_class != SCNR::Engine::URIExt
+            it 'returns the URL as a string' do
+                uri = 'http://test.com/?my=val'
+                expect(described_class.new( uri )._dump(nil)).to eq(uri)
+            end
         end
     end
 
@@ -541,6 +568,9 @@ AAAFCAYAAACNbyblAAAAHElEQVQI12P4//8/w38GIAXDIBKE0DHxgljNBAAO
 
             url = 'https://test.com:54/'
             expect(described_class.parse( url ).up_to_port).to eq('https://test.com:54')
+
+            url = 'https://test.com:80/'
+            expect(described_class.parse( url ).up_to_port).to eq('https://test.com:80')
         end
     end
 